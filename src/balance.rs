@@ -0,0 +1,102 @@
+use std::collections::BTreeMap;
+
+use chrono::{NaiveDate, Weekday};
+
+use crate::cli::BalancePer;
+use crate::duration::Duration;
+use crate::moco::model::{Activity, Employment, Schedule};
+use crate::period::week_label;
+
+/// Target and booked time of a day
+#[derive(Debug, Clone, Copy)]
+pub struct Day {
+    pub date: NaiveDate,
+    pub target: Duration,
+    pub booked: Duration,
+}
+
+/// Days from `from` to `to`, the target follows the employment of the day
+/// and is reduced by absences like vacation or public holidays
+pub fn days(
+    employments: &[Employment],
+    absences: &[Schedule],
+    activities: &[Activity],
+    from: NaiveDate,
+    to: NaiveDate,
+) -> Vec<Day> {
+    let mut booked: BTreeMap<&str, Duration> = BTreeMap::new();
+    for activity in activities {
+        let total = booked.entry(activity.date.as_str()).or_default();
+        *total = *total + activity.duration();
+    }
+
+    from.iter_days()
+        .take_while(|date| *date <= to)
+        .map(|date| {
+            let key = date.format("%Y-%m-%d").to_string();
+            let (mut am, mut pm) = employments
+                .iter()
+                .find(|employment| employment.covers(&key))
+                .map(|employment| employment.target(date))
+                .unwrap_or_default();
+            for absence in absences
+                .iter()
+                .filter(|absence| absence.date == key && absence.absence_code().is_some())
+            {
+                if absence.am || !absence.pm {
+                    am = Duration::default();
+                }
+                if absence.pm || !absence.am {
+                    pm = Duration::default();
+                }
+            }
+
+            Day {
+                date,
+                target: am + pm,
+                booked: booked.get(key.as_str()).copied().unwrap_or_default(),
+            }
+        })
+        .collect()
+}
+
+/// Sums of target and booked time per day, week or month
+pub fn group(
+    days: &[Day],
+    per: BalancePer,
+    week_start: Weekday,
+) -> Vec<(String, Duration, Duration)> {
+    let mut groups: BTreeMap<String, (Duration, Duration)> = BTreeMap::new();
+    for day in days {
+        let key = match per {
+            BalancePer::Day => day.date.format("%Y-%m-%d").to_string(),
+            BalancePer::Week => week_label(day.date, week_start),
+            BalancePer::Month => day.date.format("%Y-%m").to_string(),
+        };
+        let (target, booked) = groups.entry(key).or_default();
+        *target = *target + day.target;
+        *booked = *booked + day.booked;
+    }
+
+    groups
+        .into_iter()
+        .map(|(key, (target, booked))| (key, target, booked))
+        .collect()
+}
+
+/// Booked time at the end of the month if the remaining target is booked at
+/// the rate of the days before `today`, today is still being booked
+pub fn forecast(month: &[Day], today: NaiveDate) -> Duration {
+    let (elapsed, remaining): (Vec<&Day>, Vec<&Day>) =
+        month.iter().partition(|day| day.date < today);
+    let target: Duration = elapsed.iter().map(|day| day.target).sum();
+    let booked: Duration = elapsed.iter().map(|day| day.booked).sum();
+    let remaining: Duration = remaining.iter().map(|day| day.target).sum();
+
+    let rate = if target.is_zero() {
+        1.0
+    } else {
+        booked.hours() / target.hours()
+    };
+    booked + Duration::from_hours(remaining.hours() * rate)
+}
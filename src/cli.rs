@@ -1,4 +1,14 @@
-use clap::{Parser, Subcommand, ValueEnum};
+use std::error::Error;
+use std::path::PathBuf;
+
+use chrono::NaiveTime;
+use clap::{Args, Parser, Subcommand, ValueEnum};
+
+use crate::config::AppConfig;
+use crate::duration::{parse_time, Duration};
+use crate::moco::model::{
+    ABSENCE_ABSENCE, ABSENCE_SICK_DAY, ABSENCE_UNPLANNABLE, ABSENCE_VACATION,
+};
 
 pub fn init() -> Cli {
     Cli::parse()
@@ -13,6 +23,124 @@ pub struct Cli {
 
     #[clap(long, help = "Show additional information for bug reports")]
     pub debug: bool,
+
+    #[clap(
+        long,
+        global = true,
+        help = "Never prompt, missing answers are errors naming the needed flag"
+    )]
+    pub no_input: bool,
+
+    #[clap(
+        long,
+        global = true,
+        help = "Skip confirmations of destructive commands"
+    )]
+    pub yes: bool,
+}
+
+#[derive(Debug, Args)]
+pub struct PeriodArgs {
+    #[clap(long, group = "period_flag")]
+    pub today: bool,
+
+    #[clap(long, group = "period_flag")]
+    pub week: bool,
+
+    #[clap(long, group = "period_flag")]
+    pub last_week: bool,
+
+    #[clap(long, group = "period_flag")]
+    pub month: bool,
+
+    #[clap(long, group = "period_flag")]
+    pub last_month: bool,
+
+    #[clap(
+        long,
+        group = "period_flag",
+        help = "Period like yesterday, last week, 2024-W18, 2024-05, Q2, 2024, last 10 days or since monday"
+    )]
+    pub period: Option<String>,
+
+    #[clap(long, help = "Start of the period (date or period), defaults to today")]
+    pub from: Option<String>,
+
+    #[clap(long, help = "End of the period (date or period)")]
+    pub to: Option<String>,
+}
+
+#[derive(Debug, Args)]
+pub struct OutputArgs {
+    #[clap(
+        long,
+        value_enum,
+        help = "Output format, defaults to table on terminals and json otherwise"
+    )]
+    pub output: Option<Output>,
+}
+
+#[derive(Debug, Args)]
+pub struct ActivityArgs {
+    #[clap(long, help = "Optional tag for the activity")]
+    pub tag: Option<String>,
+
+    #[clap(
+        long,
+        conflicts_with = "not_billable",
+        help = "Mark the activity as billable"
+    )]
+    pub billable: bool,
+
+    #[clap(long, help = "Mark the activity as not billable")]
+    pub not_billable: bool,
+
+    #[clap(long, help = "Optional remote service like jira or github")]
+    pub remote_service: Option<String>,
+
+    #[clap(long, help = "Optional id of the activity in the remote service")]
+    pub remote_id: Option<String>,
+
+    #[clap(long, help = "Optional url of the activity in the remote service")]
+    pub remote_url: Option<String>,
+
+    #[clap(
+        long,
+        conflicts_with = "remote_url",
+        help = "Optional Jira issue key, links the issue as remote url"
+    )]
+    pub jira_issue: Option<String>,
+}
+
+impl ActivityArgs {
+    pub fn is_empty(&self) -> bool {
+        self.tag.is_none()
+            && self.billable().is_none()
+            && self.remote_service.is_none()
+            && self.remote_id.is_none()
+            && self.remote_url.is_none()
+            && self.jira_issue.is_none()
+    }
+
+    pub fn billable(&self) -> Option<bool> {
+        (self.billable || self.not_billable).then_some(self.billable)
+    }
+
+    /// Remote service and url, a Jira issue is linked through the configured `jira_url`
+    pub fn remote(
+        &self,
+        config: &AppConfig,
+    ) -> Result<(Option<String>, Option<String>), Box<dyn Error>> {
+        match &self.jira_issue {
+            Some(issue) => Ok((
+                self.remote_service
+                    .clone()
+                    .or_else(|| Some("jira".to_string())),
+                Some(config.jira_issue_url(issue)?),
+            )),
+            None => Ok((self.remote_service.clone(), self.remote_url.clone())),
+        }
+    }
 }
 
 #[derive(Debug, Subcommand)]
@@ -23,24 +151,61 @@ pub enum Commands {
         system: Login,
     },
     #[clap(about = "List activities", long_about = None)]
+    #[clap(mut_arg("output", |arg| arg.help("Output format, defaults to table on terminals and json otherwise. JSON, CSV and TSV leave out the sums of the days and groups and the total")))]
     List {
-        #[clap(long)]
-        today: bool,
+        #[clap(flatten)]
+        period: PeriodArgs,
 
-        #[clap(long)]
-        week: bool,
+        #[clap(
+            long,
+            help = "Only show the sums, of each day if not grouped otherwise"
+        )]
+        compact: bool,
 
-        #[clap(long)]
-        last_week: bool,
+        #[clap(
+            long,
+            value_enum,
+            value_delimiter = ',',
+            help = "Group activities with subtotals, multiple levels separated by comma"
+        )]
+        group_by: Vec<GroupBy>,
 
-        #[clap(long)]
-        month: bool,
+        #[clap(long, help = "Only activities of the project (id or name)")]
+        project: Option<String>,
 
-        #[clap(long)]
-        last_month: bool,
+        #[clap(long, help = "Only activities of the task (id or name)")]
+        task: Option<String>,
 
-        #[clap(long, help = "Sum up all activities of the day to one entry")]
-        compact: bool,
+        #[clap(long, help = "Only activities of the customer (id or name)")]
+        customer: Option<String>,
+
+        #[clap(
+            long,
+            conflicts_with = "not_billable",
+            help = "Only billable activities"
+        )]
+        billable: bool,
+
+        #[clap(long, help = "Only activities which are not billable")]
+        not_billable: bool,
+
+        #[clap(long, help = "Only billed activities")]
+        billed: bool,
+
+        #[clap(
+            long,
+            help = "Only activities synced from the remote service (e.g. jira)"
+        )]
+        synced_from: Option<String>,
+
+        #[clap(long, help = "Full-text search")]
+        search: Option<String>,
+
+        #[clap(long, help = "Show tag, billable and remote link of the activities")]
+        details: bool,
+
+        #[clap(flatten)]
+        output: OutputArgs,
     },
     #[clap(about = "Create new activity", long_about = None)]
     New {
@@ -50,62 +215,249 @@ pub enum Commands {
         #[clap(long, help = "Optional task id for the activity")]
         task: Option<i64>,
 
-        #[clap(long, help = "Optional hours in format (h.m)")]
-        hours: Option<f64>,
+        #[clap(
+            long,
+            conflicts_with_all = ["start", "end"],
+            help = "Optional duration like 1.5, 1:30, 1h30m or 90m"
+        )]
+        hours: Option<Duration>,
+
+        #[clap(long, requires = "end", value_parser = parse_time, help = "Optional start time (HH:MM), requires --end")]
+        start: Option<NaiveTime>,
+
+        #[clap(long, requires = "start", value_parser = parse_time, help = "Optional end time (HH:MM), requires --start")]
+        end: Option<NaiveTime>,
 
         #[clap(long, help = "Optional date in format (YYYY-mm-dd)")]
         date: Option<String>,
 
         #[clap(long, help = "Optional description for the activity")]
         description: Option<String>,
+
+        #[clap(flatten)]
+        fields: ActivityArgs,
     },
     #[clap(about = "Edit activity", long_about = None)]
     Edit {
         #[clap(long, help = "Optional activity id")]
         activity: Option<i64>,
+
+        #[clap(long, help = "New project id")]
+        project: Option<i64>,
+
+        #[clap(long, help = "New task id")]
+        task: Option<i64>,
+
+        #[clap(long, help = "New duration like 1.5, 1:30, 1h30m or 90m")]
+        hours: Option<Duration>,
+
+        #[clap(long, help = "New date in format (YYYY-mm-dd)")]
+        date: Option<String>,
+
+        #[clap(long, help = "New description")]
+        description: Option<String>,
+
+        #[clap(flatten)]
+        fields: ActivityArgs,
     },
     #[clap(about = "Delete activity", long_about = None)]
     Rm {
         #[clap(long, help = "Optional activity id")]
         activity: Option<i64>,
     },
-    #[clap(about = "Start/Stop activity timer", long_about = None)]
+    #[clap(about = "Start, stop or switch the activity timer, or show the running one", long_about = None)]
     Timer {
         #[clap(value_enum)]
         system: Timer,
 
         #[clap(long, help = "Optional activity id")]
         activity: Option<i64>,
+
+        #[clap(long, help = "Optional project id of today's activity to start")]
+        project: Option<i64>,
+
+        #[clap(long, help = "Optional task id of today's activity to start")]
+        task: Option<i64>,
+
+        #[clap(long, help = "Optional description of today's activity to start")]
+        description: Option<String>,
+
+        #[clap(
+            long,
+            conflicts_with = "activity",
+            help = "Start a local timer, which works offline and can be paused"
+        )]
+        local: bool,
+
+        #[clap(long, help = "Note added to the description of the local timer")]
+        note: Option<String>,
+
+        #[clap(
+            long,
+            conflicts_with = "activity",
+            help = "Switch to the task of the last stopped timer"
+        )]
+        previous: bool,
+
+        #[clap(
+            long,
+            value_enum,
+            help = "Status for status bars and prompts, the Moco timer is read from a cache refreshed once per `status_refresh`"
+        )]
+        bar: Option<Bar>,
+
+        #[clap(
+            long,
+            help = "Real duration of the timer to fix, like 1.5, 1:30 or 90m"
+        )]
+        hours: Option<Duration>,
+
+        #[clap(long, help = "Template of the status, e.g. \"{project}: {elapsed}\"")]
+        format: Option<String>,
+    },
+    #[clap(about = "Clock in/out and show presences", long_about = None)]
+    #[clap(mut_arg("output", |arg| arg.help("Output format, defaults to table on terminals and json otherwise. JSON, CSV and TSV leave out the total duration")))]
+    Presence {
+        #[clap(value_enum, default_value_t = Presence::Status)]
+        system: Presence,
+
+        #[clap(flatten)]
+        period: PeriodArgs,
+
+        #[clap(flatten)]
+        output: OutputArgs,
+    },
+    #[clap(about = "Add and list absences like vacation or sick days", long_about = None)]
+    #[clap(mut_arg("output", |arg| arg.help("Output format, defaults to table on terminals and json otherwise. JSON, CSV and TSV leave out the total days")))]
+    Absence {
+        #[clap(value_enum)]
+        system: Absence,
+
+        #[clap(long = "type", value_enum, default_value_t = AbsenceType::Vacation)]
+        absence_type: AbsenceType,
+
+        #[clap(long, value_enum, help = "Only the morning or afternoon of each day")]
+        half: Option<HalfDay>,
+
+        #[clap(long, help = "Optional comment of the absence")]
+        comment: Option<String>,
+
+        #[clap(flatten)]
+        period: PeriodArgs,
+
+        #[clap(flatten)]
+        output: OutputArgs,
+    },
+    #[clap(about = "Show the vacation balance", long_about = None)]
+    Vacation {
+        #[clap(value_enum)]
+        system: Vacation,
+
+        #[clap(long, help = "Year of the balance, defaults to the current year")]
+        year: Option<i32>,
+
+        #[clap(flatten)]
+        output: OutputArgs,
+    },
+    #[clap(about = "Show target and booked hours with the overtime balance", long_about = None)]
+    #[clap(mut_arg("output", |arg| arg.help("Output format, defaults to table on terminals and json otherwise. JSON, CSV and TSV leave out the total, the forecast is only part of tables")))]
+    Balance {
+        #[clap(flatten)]
+        period: PeriodArgs,
+
+        #[clap(long, value_enum, default_value_t = BalancePer::Day, help = "Sum the balance per day, week or month")]
+        per: BalancePer,
+
+        #[clap(flatten)]
+        output: OutputArgs,
+    },
+    #[clap(about = "Check presences and activities against working time rules", long_about = None)]
+    Check {
+        #[clap(value_enum)]
+        system: Check,
+
+        #[clap(flatten)]
+        period: PeriodArgs,
+
+        #[clap(flatten)]
+        output: OutputArgs,
     },
     #[clap(about = "Sync missing Jira Tempo logs to Moco", long_about = None)]
+    #[clap(mut_arg("output", |arg| arg.help("Output format of the sync plan, defaults to table on terminals and json otherwise")))]
     Sync {
         #[clap(value_enum, default_value_t = Sync::Jira)]
         system: Sync,
 
-        #[clap(long)]
-        today: bool,
+        #[clap(flatten)]
+        period: PeriodArgs,
 
-        #[clap(long)]
-        week: bool,
+        #[clap(long, help = "Optional project id for the activity")]
+        project: Option<i64>,
 
-        #[clap(long)]
-        last_week: bool,
+        #[clap(long, help = "Optional task id for the activity")]
+        task: Option<i64>,
 
-        #[clap(long)]
-        month: bool,
+        #[clap(long, help = "Just list what will be booked in moco from Jira")]
+        dry_run: bool,
 
-        #[clap(long)]
-        last_month: bool,
+        #[clap(flatten)]
+        output: OutputArgs,
+    },
+    #[clap(about = "Import activities from local files", long_about = None)]
+    #[clap(mut_arg("output", |arg| arg.help("Output format of the sync plan, defaults to table on terminals and json otherwise")))]
+    Import {
+        #[clap(value_enum)]
+        system: Import,
 
-        #[clap(long, help = "Optional project id for the activity")]
+        #[clap(required = true, help = "Files to import")]
+        files: Vec<PathBuf>,
+
+        #[clap(flatten)]
+        period: PeriodArgs,
+
+        #[clap(long, help = "Optional project id for entries without a mapping")]
         project: Option<i64>,
 
-        #[clap(long, help = "Optional task id for the activity")]
+        #[clap(long, help = "Optional task id for entries without a mapping")]
         task: Option<i64>,
 
-        #[clap(long, help = "Just list what will be booked in moco from Jira")]
+        #[clap(long, help = "Just list what will be booked in moco from the files")]
         dry_run: bool,
+
+        #[clap(flatten)]
+        output: OutputArgs,
     },
+    #[clap(about = "Export activities", long_about = None)]
+    Export {
+        #[clap(value_enum, default_value_t = Export::Csv)]
+        system: Export,
+
+        #[clap(long, help = "Optional output file, defaults to stdout")]
+        file: Option<PathBuf>,
+
+        #[clap(flatten)]
+        period: PeriodArgs,
+    },
+}
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq, PartialOrd, Ord, ValueEnum)]
+pub enum Output {
+    Table,
+    Json,
+    Ndjson,
+    Csv,
+    Tsv,
+    Markdown,
+}
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq, PartialOrd, Ord, ValueEnum)]
+pub enum GroupBy {
+    Date,
+    Week,
+    Project,
+    Customer,
+    Task,
+    Billable,
 }
 
 #[derive(Debug, Copy, Clone, PartialEq, Eq, PartialOrd, Ord, ValueEnum)]
@@ -118,9 +470,101 @@ pub enum Login {
 pub enum Timer {
     Start,
     Stop,
+    Switch,
+    Status,
+    Fix,
+    Pause,
+    Resume,
+    Annotate,
+}
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq, PartialOrd, Ord, ValueEnum)]
+pub enum Bar {
+    Plain,
+    Waybar,
+    I3blocks,
+}
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq, PartialOrd, Ord, ValueEnum)]
+pub enum Presence {
+    In,
+    Out,
+    Status,
+    List,
+}
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq, PartialOrd, Ord, ValueEnum)]
+pub enum Absence {
+    Add,
+    List,
+}
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq, PartialOrd, Ord, ValueEnum)]
+pub enum AbsenceType {
+    Vacation,
+    Sick,
+    Absence,
+    Unplannable,
+}
+
+impl AbsenceType {
+    pub fn name(&self) -> &'static str {
+        match self {
+            AbsenceType::Vacation => "vacation",
+            AbsenceType::Sick => "sick days",
+            AbsenceType::Absence => "absence",
+            AbsenceType::Unplannable => "unplannable absence",
+        }
+    }
+
+    pub fn code(&self) -> i64 {
+        match self {
+            AbsenceType::Vacation => ABSENCE_VACATION,
+            AbsenceType::Sick => ABSENCE_SICK_DAY,
+            AbsenceType::Absence => ABSENCE_ABSENCE,
+            AbsenceType::Unplannable => ABSENCE_UNPLANNABLE,
+        }
+    }
+}
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq, PartialOrd, Ord, ValueEnum)]
+pub enum HalfDay {
+    Am,
+    Pm,
+}
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq, PartialOrd, Ord, ValueEnum)]
+pub enum Vacation {
+    Balance,
+}
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq, PartialOrd, Ord, ValueEnum)]
+pub enum BalancePer {
+    Day,
+    Week,
+    Month,
+}
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq, PartialOrd, Ord, ValueEnum)]
+pub enum Check {
+    Worktime,
 }
 
 #[derive(Debug, Copy, Clone, PartialEq, Eq, PartialOrd, Ord, ValueEnum)]
 pub enum Sync {
     Jira,
 }
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq, PartialOrd, Ord, ValueEnum)]
+pub enum Import {
+    Org,
+    Ics,
+    Csv,
+    Toggl,
+    Activitywatch,
+}
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq, PartialOrd, Ord, ValueEnum)]
+pub enum Export {
+    Csv,
+}
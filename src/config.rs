@@ -1,6 +1,11 @@
+use chrono::{NaiveDate, Utc, Weekday};
+use chrono_tz::Tz;
+
+use crate::duration::{Duration, DurationFormat, RoundingMode};
 use config::Config;
 use serde::{Deserialize, Serialize};
 use std::{
+    collections::HashMap,
     error::Error,
     fs::{create_dir, write, File},
 };
@@ -11,12 +16,161 @@ pub struct AppConfig {
     pub moco_api_key: Option<String>,
     pub moco_user_id: Option<i64>,
     pub jira_tempo_api_key: Option<String>,
+    /// Base url of Jira (e.g. `https://company.atlassian.net`) to link issues
+    pub jira_url: Option<String>,
+    /// First day of the week for periods like `this week`, defaults to monday
+    pub week_start: Option<String>,
+    /// IANA time zone (e.g. `Europe/Berlin`) of dates like "today", defaults to the system zone
+    pub timezone: Option<String>,
+    /// `decimal` (1.50) or `clock` (1:30)
+    pub duration_format: Option<String>,
+    pub rounding: Option<Rounding>,
+    /// Warn about timers running longer than this (e.g. `10h`), defaults to 10 hours, `0` disables it
+    pub timer_warning: Option<String>,
+    /// Start local timers, which work offline and can be paused, unless `--activity` is given
+    pub local_timer: Option<bool>,
+    /// How often status bars refresh the Moco timer (e.g. `30s`), defaults to a minute
+    pub status_refresh: Option<String>,
+    /// Clock in (Moco presence) when the first timer of the day starts
+    pub auto_clock_in: Option<bool>,
+    /// Required breaks and daily maximum checked by `check worktime`
+    pub worktime: Option<Worktime>,
+    /// Start of the overtime balance (`YYYY-MM-DD`), defaults to the start of the first employment
+    pub balance_start: Option<String>,
+    /// Org-mode tag to Moco task id, used for headings without a `:MOCO_TASK:` property
+    pub org_tag_tasks: Option<HashMap<String, i64>>,
+    /// Email address used to skip declined calendar events
+    pub ics_email: Option<String>,
+    pub ics_rules: Option<Vec<IcsRule>>,
+    pub csv_mapping: Option<CsvMapping>,
+    pub toggl_rules: Option<Vec<TogglRule>>,
+    pub activitywatch_rules: Option<Vec<ActivityWatchRule>>,
+}
+
+/// Rounding of booked durations, e.g. up to 15 minutes with a minimum of 15 minutes.
+/// Durations are given like `15m`, `0:15` or `0.25`
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct Rounding {
+    pub increment: Option<String>,
+    /// `up` (default), `nearest` or `down`
+    pub mode: Option<String>,
+    pub minimum: Option<String>,
+}
+
+/// Working time rules of `check worktime`, defaults to the German working time act
+/// (30 minutes break after 6 hours, 45 after 9, at most 10 hours a day)
+#[derive(Serialize, Deserialize, Clone, Debug, Default)]
+pub struct Worktime {
+    pub breaks: Option<Vec<BreakRule>>,
+    /// `0` disables the check
+    pub max_daily: Option<String>,
+    /// Warn on `presence out` before a rule gets broken
+    pub warn_on_clock_out: Option<bool>,
+}
+
+/// Break of at least `minimum` when working longer than `after`, e.g. `30m` after `6h`
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct BreakRule {
+    pub after: String,
+    pub minimum: String,
+}
+
+/// Maps calendar events to a Moco task, all given fields have to be contained
+/// in the event (case insensitive)
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct IcsRule {
+    pub summary: Option<String>,
+    pub organizer: Option<String>,
+    pub task: i64,
+}
+
+/// Maps Toggl entries to a Moco task by client and/or project name
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct TogglRule {
+    pub client: Option<String>,
+    pub project: Option<String>,
+    pub task: i64,
+}
+
+/// Clusters ActivityWatch window events whose `app` and/or `title` contain
+/// the given text (case insensitive)
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct ActivityWatchRule {
+    pub app: Option<String>,
+    pub title: Option<String>,
+    pub description: Option<String>,
+    pub task: Option<i64>,
+}
+
+impl ActivityWatchRule {
+    pub fn matches(&self, app: &str, title: &str) -> bool {
+        let contains = |haystack: &str, needle: &String| {
+            haystack.to_lowercase().contains(&needle.to_lowercase())
+        };
+
+        (self.app.is_some() || self.title.is_some())
+            && self.app.as_ref().is_none_or(|a| contains(app, a))
+            && self.title.as_ref().is_none_or(|t| contains(title, t))
+    }
+
+    /// Description of the suggested activity
+    pub fn label(&self) -> String {
+        self.description
+            .clone()
+            .or_else(|| self.title.clone())
+            .or_else(|| self.app.clone())
+            .unwrap_or_default()
+    }
+}
+
+/// Column names of CSV imports, defaults to the columns written by `export csv`
+#[derive(Serialize, Deserialize, Clone, Debug)]
+#[serde(default)]
+pub struct CsvMapping {
+    pub delimiter: char,
+    pub id: String,
+    pub date: String,
+    pub hours: String,
+    pub seconds: String,
+    pub project: String,
+    pub task: String,
+    pub description: String,
+    pub billable: String,
+    pub tag: String,
+    pub remote_service: String,
+    pub remote_id: String,
+    pub remote_url: String,
+}
+
+impl Default for CsvMapping {
+    fn default() -> Self {
+        CsvMapping {
+            delimiter: ',',
+            id: "id".to_string(),
+            date: "date".to_string(),
+            hours: "hours".to_string(),
+            seconds: "seconds".to_string(),
+            project: "project_id".to_string(),
+            task: "task_id".to_string(),
+            description: "description".to_string(),
+            billable: "billable".to_string(),
+            tag: "tag".to_string(),
+            remote_service: "remote_service".to_string(),
+            remote_id: "remote_id".to_string(),
+            remote_url: "remote_url".to_string(),
+        }
+    }
 }
 
 fn get_config_path() -> Option<std::path::PathBuf> {
     dirs::config_dir().map(|dir| dir.join("mococli").join("mococp.json"))
 }
 
+/// Path of a state file (e.g. of the local timer) next to the config
+pub fn get_state_path(name: &str) -> Option<std::path::PathBuf> {
+    dirs::config_dir().map(|dir| dir.join("mococli").join(name))
+}
+
 pub fn init() -> Result<AppConfig, Box<dyn Error>> {
     let config_file = get_config_path();
     let config_file = match config_file {
@@ -58,4 +212,123 @@ impl AppConfig {
     pub fn has_jira_credetials(&self) -> bool {
         self.jira_tempo_api_key.is_some()
     }
+
+    pub fn jira_issue_url(&self, issue: &str) -> Result<String, String> {
+        match &self.jira_url {
+            Some(url) => Ok(format!("{}/browse/{}", url.trim_end_matches('/'), issue)),
+            None => Err("Please configure \"jira_url\" to link Jira issues".to_string()),
+        }
+    }
+
+    pub fn timezone(&self) -> Tz {
+        if let Some(timezone) = &self.timezone {
+            match timezone.parse::<Tz>() {
+                Ok(tz) => return tz,
+                Err(_) => log::warn!("Unknown timezone \"{}\", using system zone", timezone),
+            }
+        }
+
+        iana_time_zone::get_timezone()
+            .ok()
+            .and_then(|timezone| timezone.parse::<Tz>().ok())
+            .unwrap_or(Tz::UTC)
+    }
+
+    pub fn today(&self) -> NaiveDate {
+        Utc::now().with_timezone(&self.timezone()).date_naive()
+    }
+
+    pub fn duration_format(&self) -> DurationFormat {
+        self.duration_format
+            .as_ref()
+            .and_then(|format| format.parse::<DurationFormat>().ok())
+            .unwrap_or_default()
+    }
+
+    /// Applies the configured rounding, empty durations (e.g. of started timers) stay empty
+    pub fn round(&self, duration: Duration) -> Duration {
+        let Some(rounding) = &self.rounding else {
+            return duration;
+        };
+        if duration.is_zero() {
+            return duration;
+        }
+
+        let parse = |value: &Option<String>| {
+            value
+                .as_ref()
+                .and_then(|value| match value.parse::<Duration>() {
+                    Ok(duration) => Some(duration),
+                    Err(e) => {
+                        log::warn!("Invalid rounding \"{}\": {}", value, e);
+                        None
+                    }
+                })
+        };
+        let mode = rounding
+            .mode
+            .as_ref()
+            .and_then(|mode| match mode.parse::<RoundingMode>() {
+                Ok(mode) => Some(mode),
+                Err(e) => {
+                    log::warn!("{}", e);
+                    None
+                }
+            })
+            .unwrap_or_default();
+
+        let mut rounded = parse(&rounding.increment)
+            .map(|increment| duration.round(increment, mode))
+            .unwrap_or(duration);
+        if let Some(minimum) = parse(&rounding.minimum) {
+            rounded = rounded.max(minimum);
+        }
+        rounded
+    }
+
+    pub fn timer_warning(&self) -> Option<Duration> {
+        let threshold = match &self.timer_warning {
+            Some(threshold) => match threshold.parse::<Duration>() {
+                Ok(threshold) => threshold,
+                Err(e) => {
+                    log::warn!("Invalid timer warning \"{}\": {}", threshold, e);
+                    Duration::from_hours(10.0)
+                }
+            },
+            None => Duration::from_hours(10.0),
+        };
+        (!threshold.is_zero()).then_some(threshold)
+    }
+
+    pub fn status_refresh(&self) -> Duration {
+        self.status_refresh
+            .as_ref()
+            .and_then(|refresh| match refresh.parse::<Duration>() {
+                Ok(refresh) => Some(refresh),
+                Err(e) => {
+                    log::warn!("Invalid status refresh \"{}\": {}", refresh, e);
+                    None
+                }
+            })
+            .unwrap_or(Duration::from_seconds(60))
+    }
+
+    pub fn balance_start(&self) -> Option<NaiveDate> {
+        self.balance_start.as_ref().and_then(|start| {
+            match NaiveDate::parse_from_str(start, "%Y-%m-%d") {
+                Ok(start) => Some(start),
+                Err(e) => {
+                    log::warn!("Invalid balance start \"{}\": {}", start, e);
+                    None
+                }
+            }
+        })
+    }
+
+    pub fn week_start(&self) -> Weekday {
+        self.week_start
+            .as_ref()
+            .and_then(|day| day.parse::<Weekday>().ok())
+            .unwrap_or(Weekday::Mon)
+    }
 }
@@ -0,0 +1,222 @@
+use std::fmt::Display;
+use std::iter::Sum;
+use std::ops::{Add, Sub};
+use std::str::FromStr;
+
+use chrono::NaiveTime;
+use serde::{Deserialize, Serialize};
+
+/// How durations are shown, as decimal hours (`1.50`) or as clock time (`1:30`)
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub enum DurationFormat {
+    #[default]
+    Decimal,
+    Clock,
+}
+
+impl FromStr for DurationFormat {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "decimal" => Ok(DurationFormat::Decimal),
+            "clock" => Ok(DurationFormat::Clock),
+            _ => Err(format!(
+                "Unknown duration format \"{}\", use decimal or clock",
+                s
+            )),
+        }
+    }
+}
+
+/// Direction in which durations are rounded to an increment
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub enum RoundingMode {
+    #[default]
+    Up,
+    Nearest,
+    Down,
+}
+
+impl FromStr for RoundingMode {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "up" => Ok(RoundingMode::Up),
+            "nearest" => Ok(RoundingMode::Nearest),
+            "down" => Ok(RoundingMode::Down),
+            _ => Err(format!(
+                "Unknown rounding mode \"{}\", use up, nearest or down",
+                s
+            )),
+        }
+    }
+}
+
+/// Exact duration of an activity in seconds
+#[derive(
+    Debug, Default, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize,
+)]
+#[serde(transparent)]
+pub struct Duration {
+    seconds: i64,
+}
+
+impl Duration {
+    pub fn from_seconds(seconds: i64) -> Self {
+        Duration { seconds }
+    }
+
+    pub fn from_hours(hours: f64) -> Self {
+        Duration {
+            seconds: (hours * 60.0 * 60.0).round() as i64,
+        }
+    }
+
+    /// Duration between two times of the same day
+    pub fn between(start: NaiveTime, end: NaiveTime) -> Result<Self, String> {
+        if end < start {
+            return Err(format!("End {} is before start {}", end, start));
+        }
+        Ok(Duration {
+            seconds: (end - start).num_seconds(),
+        })
+    }
+
+    pub fn seconds(&self) -> i64 {
+        self.seconds
+    }
+
+    pub fn is_zero(&self) -> bool {
+        self.seconds == 0
+    }
+
+    pub fn hours(&self) -> f64 {
+        self.seconds as f64 / 60.0 / 60.0
+    }
+
+    /// Rounds to a multiple of the increment, zero increments keep the duration
+    pub fn round(&self, increment: Duration, mode: RoundingMode) -> Duration {
+        if increment.seconds <= 0 {
+            return *self;
+        }
+        let steps = self.seconds.div_euclid(increment.seconds);
+        let rest = self.seconds.rem_euclid(increment.seconds);
+        let steps = match mode {
+            RoundingMode::Up if rest > 0 => steps + 1,
+            RoundingMode::Nearest if rest * 2 >= increment.seconds => steps + 1,
+            _ => steps,
+        };
+        Duration {
+            seconds: steps * increment.seconds,
+        }
+    }
+
+    pub fn format(&self, format: DurationFormat) -> String {
+        match format {
+            DurationFormat::Decimal => format!("{:.2}", self.hours()),
+            DurationFormat::Clock => {
+                let sign = if self.seconds < 0 { "-" } else { "" };
+                let minutes = (self.seconds.abs() as f64 / 60.0).round() as i64;
+                format!("{}{}:{:02}", sign, minutes / 60, minutes % 60)
+            }
+        }
+    }
+}
+
+/// Parses `1.5` (hours), `1,5`, `1:30`, `1h30m`, `1h`, `90m` and `45s`
+impl FromStr for Duration {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let value = s.trim().to_lowercase().replace(' ', "");
+        let invalid = || {
+            format!(
+                "Invalid duration \"{}\", use e.g. 1.5, 1:30, 1h30m or 90m",
+                s.trim()
+            )
+        };
+
+        if value.is_empty() {
+            return Err(invalid());
+        }
+
+        if let Some((hours, minutes)) = value.split_once(':') {
+            let hours = hours.parse::<u32>().map_err(|_| invalid())?;
+            let minutes = minutes.parse::<u32>().map_err(|_| invalid())?;
+            if minutes >= 60 {
+                return Err(invalid());
+            }
+            return Ok(Duration::from_seconds(
+                hours as i64 * 60 * 60 + minutes as i64 * 60,
+            ));
+        }
+
+        if let Ok(hours) = value.replace(',', ".").parse::<f64>() {
+            if hours < 0.0 || !hours.is_finite() {
+                return Err(invalid());
+            }
+            return Ok(Duration::from_hours(hours));
+        }
+
+        let mut seconds = 0.0;
+        let mut number = String::new();
+        for c in value.chars() {
+            match c {
+                '0'..='9' | '.' | ',' => number.push(if c == ',' { '.' } else { c }),
+                'h' | 'm' | 's' => {
+                    let amount = number.parse::<f64>().map_err(|_| invalid())?;
+                    number.clear();
+                    seconds += amount
+                        * match c {
+                            'h' => 60.0 * 60.0,
+                            'm' => 60.0,
+                            _ => 1.0,
+                        };
+                }
+                _ => return Err(invalid()),
+            }
+        }
+
+        if !number.is_empty() {
+            return Err(invalid());
+        }
+
+        Ok(Duration::from_seconds(seconds.round() as i64))
+    }
+}
+
+impl Display for Duration {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.format(DurationFormat::Decimal))
+    }
+}
+
+impl Add for Duration {
+    type Output = Duration;
+
+    fn add(self, rhs: Self) -> Self::Output {
+        Duration::from_seconds(self.seconds + rhs.seconds)
+    }
+}
+
+impl Sub for Duration {
+    type Output = Duration;
+
+    fn sub(self, rhs: Self) -> Self::Output {
+        Duration::from_seconds(self.seconds - rhs.seconds)
+    }
+}
+
+impl Sum for Duration {
+    fn sum<I: Iterator<Item = Self>>(iter: I) -> Self {
+        iter.fold(Duration::default(), |sum, duration| sum + duration)
+    }
+}
+
+pub fn parse_time(value: &str) -> Result<NaiveTime, String> {
+    NaiveTime::parse_from_str(value, "%H:%M")
+        .or_else(|_| NaiveTime::parse_from_str(value, "%H:%M:%S"))
+        .map_err(|_| format!("Invalid time \"{}\", expected HH:MM", value))
+}
@@ -0,0 +1,90 @@
+use std::{error::Error, io::Write};
+
+use serde::Serialize;
+use serde_json::Value;
+
+use crate::moco::model::Activity;
+
+/// Flat representation of an `Activity`, the column names are the defaults
+/// of `import csv` so exported files can be imported again
+#[derive(Debug, Serialize)]
+struct ActivityRecord<'a> {
+    id: i64,
+    date: &'a str,
+    hours: f64,
+    seconds: i64,
+    description: &'a str,
+    billed: bool,
+    billable: bool,
+    tag: &'a str,
+    remote_service: &'a str,
+    remote_id: &'a str,
+    remote_url: String,
+    project_id: i64,
+    project_name: &'a str,
+    project_billable: bool,
+    task_id: i64,
+    task_name: &'a str,
+    task_billable: bool,
+    customer_id: i64,
+    customer_name: &'a str,
+    user_id: i64,
+    user_firstname: &'a str,
+    user_lastname: &'a str,
+    timer_started_at: String,
+    created_at: &'a str,
+    updated_at: &'a str,
+    hourly_rate: f64,
+}
+
+fn value_to_string(value: &Value) -> String {
+    match value {
+        Value::Null => String::new(),
+        Value::String(value) => value.clone(),
+        value => value.to_string(),
+    }
+}
+
+pub fn write_csv(
+    activities: &[Activity],
+    delimiter: char,
+    writer: impl Write,
+) -> Result<(), Box<dyn Error>> {
+    let mut writer = ::csv::WriterBuilder::new()
+        .delimiter(delimiter as u8)
+        .from_writer(writer);
+
+    for activity in activities {
+        writer.serialize(ActivityRecord {
+            id: activity.id,
+            date: &activity.date,
+            hours: activity.hours,
+            seconds: activity.seconds,
+            description: activity.description.as_deref().unwrap_or_default(),
+            billed: activity.billed,
+            billable: activity.billable,
+            tag: &activity.tag,
+            remote_service: activity.remote_service.as_deref().unwrap_or_default(),
+            remote_id: activity.remote_id.as_deref().unwrap_or_default(),
+            remote_url: value_to_string(&activity.remote_url),
+            project_id: activity.project.id,
+            project_name: &activity.project.name,
+            project_billable: activity.project.billable,
+            task_id: activity.task.id,
+            task_name: &activity.task.name,
+            task_billable: activity.task.billable,
+            customer_id: activity.customer.id,
+            customer_name: &activity.customer.name,
+            user_id: activity.user.id,
+            user_firstname: &activity.user.firstname,
+            user_lastname: &activity.user.lastname,
+            timer_started_at: value_to_string(&activity.timer_started_at),
+            created_at: &activity.created_at,
+            updated_at: &activity.updated_at,
+            hourly_rate: activity.hourly_rate,
+        })?;
+    }
+
+    writer.flush()?;
+    Ok(())
+}
@@ -0,0 +1,126 @@
+use std::collections::BTreeMap;
+use std::error::Error;
+
+use chrono::{DateTime, Duration, FixedOffset, NaiveDate};
+use chrono_tz::Tz;
+use serde::Deserialize;
+use serde_json::Value;
+
+use crate::config::ActivityWatchRule;
+use crate::utils::stable_hash;
+
+/// Events shorter than this in sum are not suggested
+const MIN_SECONDS: i64 = 60;
+
+#[derive(Debug, Deserialize)]
+struct Bucket {
+    #[serde(rename = "type")]
+    bucket_type: String,
+    events: Vec<Event>,
+}
+
+#[derive(Debug, Deserialize)]
+struct Event {
+    timestamp: String,
+    duration: f64,
+    data: Value,
+}
+
+/// Window events of one day clustered by a rule or by the app name
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Suggestion {
+    pub date: NaiveDate,
+    pub description: String,
+    pub task: Option<i64>,
+    pub seconds: i64,
+    pub remote_id: String,
+}
+
+/// Parses an ActivityWatch bucket export, time the user was away from the
+/// keyboard is not counted
+pub fn parse(
+    content: &str,
+    rules: &[ActivityWatchRule],
+    timezone: &Tz,
+) -> Result<Vec<Suggestion>, Box<dyn Error>> {
+    let export: Value = serde_json::from_str(content)?;
+    let buckets = export.get("buckets").unwrap_or(&export).clone();
+    let buckets: BTreeMap<String, Bucket> = serde_json::from_value(buckets)?;
+
+    let afk: Vec<(DateTime<FixedOffset>, DateTime<FixedOffset>)> = buckets
+        .values()
+        .filter(|bucket| bucket.bucket_type == "afkstatus")
+        .flat_map(|bucket| bucket.events.iter())
+        .filter(|event| event.data.get("status").and_then(Value::as_str) == Some("afk"))
+        .filter_map(|event| {
+            let start = DateTime::parse_from_rfc3339(&event.timestamp).ok()?;
+            Some((start, start + seconds(event.duration)))
+        })
+        .collect();
+
+    let mut clusters: BTreeMap<(NaiveDate, usize, String), i64> = BTreeMap::new();
+
+    for event in buckets
+        .values()
+        .filter(|bucket| bucket.bucket_type == "currentwindow")
+        .flat_map(|bucket| bucket.events.iter())
+    {
+        let start = DateTime::parse_from_rfc3339(&event.timestamp)?;
+        let end = start + seconds(event.duration);
+        let away: i64 = afk
+            .iter()
+            .map(|(afk_start, afk_end)| {
+                (end.min(*afk_end) - start.max(*afk_start))
+                    .num_seconds()
+                    .max(0)
+            })
+            .sum();
+
+        let active = (end - start).num_seconds() - away;
+        if active <= 0 {
+            continue;
+        }
+
+        let app = event
+            .data
+            .get("app")
+            .and_then(Value::as_str)
+            .unwrap_or_default();
+        let title = event
+            .data
+            .get("title")
+            .and_then(Value::as_str)
+            .unwrap_or_default();
+
+        // Clusters of rules are keyed by the rule index, others by the app name
+        let key = match rules.iter().position(|rule| rule.matches(app, title)) {
+            Some(index) => (index, String::new()),
+            None => (rules.len(), app.to_string()),
+        };
+
+        *clusters
+            .entry((start.with_timezone(timezone).date_naive(), key.0, key.1))
+            .or_insert(0) += active;
+    }
+
+    Ok(clusters
+        .into_iter()
+        .filter(|(_, seconds)| *seconds >= MIN_SECONDS)
+        .map(|((date, rule_index, app), seconds)| {
+            let rule = rules.get(rule_index);
+            let description = rule.map(|rule| rule.label()).unwrap_or_else(|| app.clone());
+
+            Suggestion {
+                date,
+                remote_id: stable_hash(&format!("{}|{}", date, description)),
+                description,
+                task: rule.and_then(|rule| rule.task),
+                seconds,
+            }
+        })
+        .collect())
+}
+
+fn seconds(seconds: f64) -> Duration {
+    Duration::milliseconds((seconds * 1000.0) as i64)
+}
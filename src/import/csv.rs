@@ -0,0 +1,262 @@
+use std::collections::HashMap;
+
+use chrono::NaiveDate;
+
+use crate::config::CsvMapping;
+use crate::duration::Duration;
+use crate::moco::model::{Project, ProjectTask};
+use crate::utils::{find_project_task, stable_hash};
+
+/// A validated CSV row, rows with an `id` update the existing activity
+#[derive(Debug, Clone)]
+pub struct CsvRow {
+    pub id: Option<i64>,
+    pub date: NaiveDate,
+    pub duration: Duration,
+    pub project: Project,
+    pub task: ProjectTask,
+    pub description: String,
+    pub billable: Option<bool>,
+    pub tag: Option<String>,
+    pub remote_service: Option<String>,
+    pub remote_id: Option<String>,
+    pub remote_url: Option<String>,
+}
+
+/// Parses and validates all rows, returns every error with its line number
+/// so nothing is booked from a partially valid file
+pub fn parse(
+    content: &str,
+    mapping: &CsvMapping,
+    projects: &[Project],
+    project: Option<i64>,
+    task: Option<i64>,
+) -> Result<Vec<CsvRow>, Vec<String>> {
+    let mut reader = ::csv::ReaderBuilder::new()
+        .delimiter(mapping.delimiter as u8)
+        .flexible(true)
+        .from_reader(content.as_bytes());
+
+    let headers: HashMap<String, usize> = match reader.headers() {
+        Ok(headers) => headers
+            .iter()
+            .enumerate()
+            .map(|(index, header)| (header.trim().to_lowercase(), index))
+            .collect(),
+        Err(e) => return Err(vec![format!("Could not read header: {}", e)]),
+    };
+
+    if !headers.contains_key(&mapping.date.to_lowercase()) {
+        return Err(vec![format!("Column \"{}\" is missing", mapping.date)]);
+    }
+    if !headers.contains_key(&mapping.hours.to_lowercase())
+        && !headers.contains_key(&mapping.seconds.to_lowercase())
+    {
+        return Err(vec![format!(
+            "Column \"{}\" or \"{}\" is missing",
+            mapping.hours, mapping.seconds
+        )]);
+    }
+
+    let mut rows = vec![];
+    let mut errors = vec![];
+
+    for record in reader.records() {
+        let record = match record {
+            Ok(record) => record,
+            Err(e) => {
+                errors.push(e.to_string());
+                continue;
+            }
+        };
+        let line = record.position().map_or(0, |p| p.line());
+
+        let field = |column: &str| {
+            headers
+                .get(&column.to_lowercase())
+                .and_then(|index| record.get(*index))
+                .map(|value| value.trim().to_string())
+                .filter(|value| !value.is_empty())
+        };
+
+        match parse_row(&field, mapping, projects, project, task) {
+            Ok(row) => rows.push(row),
+            Err(e) => errors.push(format!("Line {}: {}", line, e)),
+        }
+    }
+
+    if errors.is_empty() {
+        Ok(rows)
+    } else {
+        Err(errors)
+    }
+}
+
+fn parse_row(
+    field: &dyn Fn(&str) -> Option<String>,
+    mapping: &CsvMapping,
+    projects: &[Project],
+    project: Option<i64>,
+    task: Option<i64>,
+) -> Result<CsvRow, String> {
+    let id = field(&mapping.id)
+        .map(|id| {
+            id.parse::<i64>()
+                .map_err(|_| format!("Invalid activity id \"{}\"", id))
+        })
+        .transpose()?;
+
+    let date = field(&mapping.date).ok_or("Date is missing")?;
+    let date = NaiveDate::parse_from_str(&date, "%Y-%m-%d")
+        .map_err(|_| format!("Invalid date \"{}\", expected YYYY-MM-DD", date))?;
+
+    let seconds = field(&mapping.seconds)
+        .map(|seconds| {
+            seconds
+                .parse::<i64>()
+                .map(Duration::from_seconds)
+                .map_err(|_| format!("Invalid seconds \"{}\"", seconds))
+        })
+        .transpose()?;
+    let hours = field(&mapping.hours)
+        .map(|hours| {
+            hours
+                .parse::<Duration>()
+                .map_err(|_| format!("Invalid hours \"{}\"", hours))
+        })
+        .transpose()?;
+    // Exports contain the exact seconds next to the hours rounded to two decimals,
+    // an edit of only one of them can not be told apart from the other
+    let duration = match (seconds, hours) {
+        (Some(seconds), Some(hours)) => {
+            let cents = |duration: Duration| (duration.hours() * 100.0).round() as i64;
+            if cents(seconds) != cents(hours) {
+                return Err(format!(
+                    "Hours ({}) and seconds ({}) disagree, clear one of them",
+                    hours.hours(),
+                    seconds.seconds()
+                ));
+            }
+            seconds
+        }
+        (Some(duration), None) | (None, Some(duration)) => duration,
+        (None, None) => return Err("Duration is missing".to_string()),
+    };
+
+    let billable = field(&mapping.billable)
+        .map(|billable| match billable.to_lowercase().as_str() {
+            "true" | "yes" | "1" => Ok(true),
+            "false" | "no" | "0" => Ok(false),
+            _ => Err(format!("Invalid billable \"{}\"", billable)),
+        })
+        .transpose()?;
+
+    let (project, task) = resolve_task(
+        field(&mapping.project),
+        field(&mapping.task),
+        projects,
+        project,
+        task,
+    )?;
+
+    let remote_id = field(&mapping.remote_id);
+    let description = field(&mapping.description).unwrap_or_default();
+
+    Ok(CsvRow {
+        id,
+        date,
+        duration,
+        description: description.clone(),
+        billable,
+        tag: field(&mapping.tag),
+        // New rows without a remote id get a stable one so a file can be imported twice
+        remote_service: field(&mapping.remote_service)
+            .or_else(|| (id.is_none() && remote_id.is_none()).then(|| "csv".to_string())),
+        remote_id: remote_id.or_else(|| {
+            id.is_none().then(|| {
+                stable_hash(&format!(
+                    "{}|{}|{}|{}|{}",
+                    date,
+                    duration.seconds(),
+                    project.id,
+                    task.id,
+                    description
+                ))
+            })
+        }),
+        remote_url: field(&mapping.remote_url),
+        project,
+        task,
+    })
+}
+
+/// Projects and tasks can be given by id or by name, missing values fall back
+/// to the `--project`/`--task` arguments
+fn resolve_task(
+    project_value: Option<String>,
+    task_value: Option<String>,
+    projects: &[Project],
+    project: Option<i64>,
+    task: Option<i64>,
+) -> Result<(Project, ProjectTask), String> {
+    let project = match (project_value, project) {
+        (Some(value), _) => Some(
+            projects
+                .iter()
+                .find(|p| {
+                    value.parse::<i64>().ok() == Some(p.id) || p.name.eq_ignore_ascii_case(&value)
+                })
+                .ok_or(format!("Project \"{}\" is not assigned to you", value))?,
+        ),
+        (None, Some(id)) => Some(
+            projects
+                .iter()
+                .find(|p| p.id == id)
+                .ok_or(format!("Project {} is not assigned to you", id))?,
+        ),
+        (None, None) => None,
+    };
+
+    let task_value = task_value.or_else(|| task.map(|id| id.to_string()));
+    let task_value = task_value.ok_or("Task is missing")?;
+
+    let matches_task = |t: &ProjectTask| {
+        task_value.parse::<i64>().ok() == Some(t.id) || t.name.eq_ignore_ascii_case(&task_value)
+    };
+
+    if let Some(project) = project {
+        return project
+            .tasks
+            .iter()
+            .find(|t| matches_task(t))
+            .map(|t| (project.clone(), t.clone()))
+            .ok_or(format!(
+                "Task \"{}\" does not exist in project \"{}\"",
+                task_value, project.name
+            ));
+    }
+
+    if let Some(found) = task_value
+        .parse::<i64>()
+        .ok()
+        .and_then(|id| find_project_task(projects, id))
+    {
+        return Ok(found);
+    }
+
+    let mut found = projects.iter().flat_map(|p| {
+        p.tasks
+            .iter()
+            .filter(|t| matches_task(t))
+            .map(move |t| (p.clone(), t.clone()))
+    });
+
+    match (found.next(), found.next()) {
+        (Some(found), None) => Ok(found),
+        (Some(_), Some(_)) => Err(format!(
+            "Task \"{}\" exists in multiple projects, add the project",
+            task_value
+        )),
+        (None, _) => Err(format!("Task \"{}\" is not assigned to you", task_value)),
+    }
+}
@@ -0,0 +1,462 @@
+use std::collections::HashMap;
+
+use chrono::{Datelike, Duration, NaiveDate, NaiveDateTime, NaiveTime, TimeZone, Utc, Weekday};
+
+use chrono_tz::Tz;
+
+use crate::config::IcsRule;
+
+/// Upper bound of generated occurrences per recurring event
+const MAX_OCCURRENCES: usize = 10_000;
+
+/// One occurrence of a calendar event
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct IcsEvent {
+    pub uid: String,
+    pub summary: String,
+    pub organizer: Option<String>,
+    pub date: NaiveDate,
+    pub seconds: i64,
+    pub remote_id: String,
+}
+
+struct Property {
+    name: String,
+    params: Vec<(String, String)>,
+    value: String,
+}
+
+impl Property {
+    fn param(&self, name: &str) -> Option<&str> {
+        self.params
+            .iter()
+            .find(|(key, _)| key.eq_ignore_ascii_case(name))
+            .map(|(_, value)| value.as_str())
+    }
+}
+
+#[derive(Default)]
+struct RawEvent {
+    uid: String,
+    summary: String,
+    organizer: Option<String>,
+    start: Option<NaiveDateTime>,
+    end: Option<NaiveDateTime>,
+    duration: Option<Duration>,
+    rrule: Option<String>,
+    exdates: Vec<NaiveDateTime>,
+    recurrence_id: Option<NaiveDateTime>,
+    cancelled: bool,
+    declined: bool,
+}
+
+/// Returns all occurrences between `from` and `to` in the given time zone,
+/// all-day, cancelled and events declined by `attendee` are skipped
+pub fn parse(
+    content: &str,
+    from: NaiveDate,
+    to: NaiveDate,
+    attendee: Option<&str>,
+    timezone: &Tz,
+) -> Vec<IcsEvent> {
+    let events = parse_events(content, attendee, timezone);
+
+    let mut overrides: HashMap<(String, NaiveDateTime), &RawEvent> = HashMap::new();
+    for event in events.iter() {
+        if let Some(recurrence_id) = event.recurrence_id {
+            overrides.insert((event.uid.clone(), recurrence_id), event);
+        }
+    }
+
+    let mut occurrences = vec![];
+    for event in events.iter().filter(|e| e.recurrence_id.is_none()) {
+        let Some(start) = event.start else {
+            continue;
+        };
+
+        // Occurrences moved into the range from after it are expanded as well
+        let expand_to = overrides
+            .keys()
+            .filter(|(uid, _)| *uid == event.uid)
+            .map(|(_, recurrence_id)| recurrence_id.date())
+            .fold(to, NaiveDate::max);
+        let starts = match &event.rrule {
+            Some(rrule) => expand_rrule(start, rrule, expand_to, timezone),
+            None => vec![start],
+        };
+
+        for occurrence_start in starts {
+            if event.exdates.contains(&occurrence_start) {
+                continue;
+            }
+
+            let occurrence = overrides
+                .get(&(event.uid.clone(), occurrence_start))
+                .copied()
+                .unwrap_or(event);
+
+            let Some(occurrence_start) = (if occurrence.recurrence_id.is_some() {
+                occurrence.start
+            } else {
+                Some(occurrence_start)
+            }) else {
+                continue;
+            };
+
+            if occurrence.cancelled || occurrence.declined {
+                continue;
+            }
+
+            let date = occurrence_start.date();
+            if date < from || date > to {
+                continue;
+            }
+
+            let seconds = occurrence
+                .duration
+                .or_else(|| {
+                    occurrence
+                        .end
+                        .map(|end| end - occurrence.start.unwrap_or(end))
+                })
+                .or_else(|| event.end.map(|end| end - start))
+                .map(|duration| duration.num_seconds())
+                .unwrap_or(0);
+
+            if seconds <= 0 {
+                continue;
+            }
+
+            occurrences.push(IcsEvent {
+                uid: event.uid.clone(),
+                summary: occurrence.summary.clone(),
+                organizer: occurrence
+                    .organizer
+                    .clone()
+                    .or_else(|| event.organizer.clone()),
+                date,
+                seconds,
+                remote_id: format!("{}_{}", event.uid, date.format("%Y%m%d")),
+            });
+        }
+    }
+
+    occurrences.sort_by_key(|occurrence| occurrence.date);
+    occurrences
+}
+
+pub fn find_task(rules: &[IcsRule], event: &IcsEvent) -> Option<i64> {
+    let contains = |haystack: &str, needle: &str| {
+        haystack
+            .to_lowercase()
+            .contains(needle.to_lowercase().as_str())
+    };
+
+    rules
+        .iter()
+        .find(|rule| {
+            rule.summary
+                .as_ref()
+                .is_none_or(|summary| contains(&event.summary, summary))
+                && rule.organizer.as_ref().is_none_or(|organizer| {
+                    event
+                        .organizer
+                        .as_ref()
+                        .is_some_and(|o| contains(o, organizer))
+                })
+        })
+        .map(|rule| rule.task)
+}
+
+fn parse_events(content: &str, attendee: Option<&str>, timezone: &Tz) -> Vec<RawEvent> {
+    // Long lines are folded by a line break followed by a space or tab
+    let unfolded = content
+        .replace("\r\n", "\n")
+        .replace("\n ", "")
+        .replace("\n\t", "");
+
+    let mut events = vec![];
+    let mut event: Option<RawEvent> = None;
+    let mut nested_components = 0;
+
+    for property in unfolded.lines().filter_map(parse_property) {
+        match (property.name.as_str(), property.value.as_str()) {
+            ("BEGIN", "VEVENT") => event = Some(RawEvent::default()),
+            ("END", "VEVENT") => events.extend(event.take()),
+            ("BEGIN", _) if event.is_some() => nested_components += 1,
+            ("END", _) if event.is_some() => nested_components -= 1,
+            _ => {
+                if let (Some(event), 0) = (event.as_mut(), nested_components) {
+                    apply_property(event, &property, attendee, timezone);
+                }
+            }
+        }
+    }
+
+    events
+}
+
+fn apply_property(
+    event: &mut RawEvent,
+    property: &Property,
+    attendee: Option<&str>,
+    timezone: &Tz,
+) {
+    match property.name.as_str() {
+        "UID" => event.uid = property.value.clone(),
+        "SUMMARY" => event.summary = unescape(&property.value),
+        "ORGANIZER" => event.organizer = Some(strip_mailto(&property.value).to_string()),
+        "DTSTART" => event.start = parse_date_time(property, timezone),
+        "DTEND" => event.end = parse_date_time(property, timezone),
+        "DURATION" => event.duration = parse_duration(&property.value),
+        "RRULE" => event.rrule = Some(property.value.clone()),
+        "EXDATE" => event
+            .exdates
+            .extend(property.value.split(',').filter_map(|value| {
+                parse_date_time(
+                    &Property {
+                        name: property.name.clone(),
+                        params: property.params.clone(),
+                        value: value.to_string(),
+                    },
+                    timezone,
+                )
+            })),
+        "RECURRENCE-ID" => event.recurrence_id = parse_date_time(property, timezone),
+        "STATUS" => event.cancelled = property.value.eq_ignore_ascii_case("CANCELLED"),
+        "ATTENDEE"
+            if attendee.is_some_and(|a| strip_mailto(&property.value).eq_ignore_ascii_case(a))
+                && property
+                    .param("PARTSTAT")
+                    .is_some_and(|s| s.eq_ignore_ascii_case("DECLINED")) =>
+        {
+            event.declined = true
+        }
+        _ => {}
+    }
+}
+
+fn parse_property(line: &str) -> Option<Property> {
+    // The value starts after the first colon which is not inside a quoted parameter
+    let mut quoted = false;
+    let split = line.char_indices().find(|(_, c)| {
+        if *c == '"' {
+            quoted = !quoted;
+        }
+        *c == ':' && !quoted
+    })?;
+
+    let (head, value) = (&line[..split.0], &line[split.0 + 1..]);
+    let mut parts = head.split(';');
+    let name = parts.next()?.to_uppercase();
+    let params = parts
+        .filter_map(|param| param.split_once('='))
+        .map(|(key, value)| (key.to_uppercase(), value.trim_matches('"').to_string()))
+        .collect();
+
+    Some(Property {
+        name,
+        params,
+        value: value.to_string(),
+    })
+}
+
+/// Converts date times to the local time of `timezone`, values without a known
+/// `TZID` are taken as local time. All-day values (`VALUE=DATE`) are ignored
+/// since they carry no duration
+fn parse_date_time(property: &Property, timezone: &Tz) -> Option<NaiveDateTime> {
+    if property
+        .param("VALUE")
+        .is_some_and(|v| v.eq_ignore_ascii_case("DATE"))
+    {
+        return None;
+    }
+
+    let value = property.value.trim();
+    if let Some(utc) = value.strip_suffix('Z') {
+        let date_time = NaiveDateTime::parse_from_str(utc, "%Y%m%dT%H%M%S").ok()?;
+        return Some(
+            Utc.from_utc_datetime(&date_time)
+                .with_timezone(timezone)
+                .naive_local(),
+        );
+    }
+
+    let date_time = NaiveDateTime::parse_from_str(value, "%Y%m%dT%H%M%S").ok()?;
+    match property
+        .param("TZID")
+        .and_then(|tzid| tzid.parse::<Tz>().ok())
+    {
+        Some(tzid) => tzid
+            .from_local_datetime(&date_time)
+            .earliest()
+            .map(|date_time| date_time.with_timezone(timezone).naive_local()),
+        None => Some(date_time),
+    }
+}
+
+/// Parses durations like `PT1H30M` or `P1D`
+fn parse_duration(value: &str) -> Option<Duration> {
+    let (negative, value) = match value.strip_prefix('-') {
+        Some(value) => (true, value),
+        None => (false, value.strip_prefix('+').unwrap_or(value)),
+    };
+
+    let mut seconds = 0;
+    let mut number = String::new();
+    for c in value.strip_prefix('P')?.chars() {
+        match c {
+            '0'..='9' => number.push(c),
+            'T' => {}
+            'W' | 'D' | 'H' | 'M' | 'S' => {
+                let amount = number.parse::<i64>().ok()?;
+                number.clear();
+                seconds += amount
+                    * match c {
+                        'W' => 7 * 24 * 60 * 60,
+                        'D' => 24 * 60 * 60,
+                        'H' => 60 * 60,
+                        'M' => 60,
+                        _ => 1,
+                    };
+            }
+            _ => return None,
+        }
+    }
+
+    Some(Duration::seconds(if negative { -seconds } else { seconds }))
+}
+
+/// Expands `FREQ`, `INTERVAL`, `COUNT`, `UNTIL` and weekly `BYDAY` rules up to `to`
+fn expand_rrule(
+    start: NaiveDateTime,
+    rrule: &str,
+    to: NaiveDate,
+    timezone: &Tz,
+) -> Vec<NaiveDateTime> {
+    let rule: HashMap<String, String> = rrule
+        .split(';')
+        .filter_map(|part| part.split_once('='))
+        .map(|(key, value)| (key.to_uppercase(), value.to_string()))
+        .collect();
+
+    let interval = rule
+        .get("INTERVAL")
+        .and_then(|i| i.parse::<i64>().ok())
+        .unwrap_or(1)
+        .max(1);
+    let count = rule.get("COUNT").and_then(|c| c.parse::<usize>().ok());
+    let until = rule.get("UNTIL").and_then(|until| {
+        parse_date_time(
+            &Property {
+                name: "UNTIL".to_string(),
+                params: vec![],
+                value: until.clone(),
+            },
+            timezone,
+        )
+        .or_else(|| {
+            NaiveDate::parse_from_str(until, "%Y%m%d")
+                .ok()
+                .map(|date| date.and_time(NaiveTime::MIN) + Duration::days(1))
+        })
+    });
+    let by_day: Vec<Weekday> = rule
+        .get("BYDAY")
+        .map(|days| {
+            days.split(',')
+                .filter_map(|day| {
+                    parse_weekday(day.trim_start_matches(|c: char| !c.is_alphabetic()))
+                })
+                .collect()
+        })
+        .unwrap_or_default();
+
+    let frequency = rule
+        .get("FREQ")
+        .map(|f| f.to_uppercase())
+        .unwrap_or_default();
+    let time = start.time();
+    let start_date = start.date();
+
+    let mut occurrences = vec![];
+    for period in 0..MAX_OCCURRENCES as i64 {
+        let step = period * interval;
+        let dates: Vec<NaiveDate> = match frequency.as_str() {
+            "DAILY" => vec![start_date + Duration::days(step)],
+            "WEEKLY" if by_day.is_empty() => vec![start_date + Duration::weeks(step)],
+            "WEEKLY" => {
+                let week_start = start_date
+                    - Duration::days(start_date.weekday().num_days_from_monday() as i64)
+                    + Duration::weeks(step);
+                let mut dates: Vec<NaiveDate> = by_day
+                    .iter()
+                    .map(|day| week_start + Duration::days(day.num_days_from_monday() as i64))
+                    .filter(|date| *date >= start_date)
+                    .collect();
+                dates.sort();
+                dates
+            }
+            "MONTHLY" => {
+                let months = start_date.month0() as i64 + step;
+                NaiveDate::from_ymd_opt(
+                    start_date.year() + (months / 12) as i32,
+                    (months % 12) as u32 + 1,
+                    start_date.day(),
+                )
+                .into_iter()
+                .collect()
+            }
+            "YEARLY" => NaiveDate::from_ymd_opt(
+                start_date.year() + step as i32,
+                start_date.month(),
+                start_date.day(),
+            )
+            .into_iter()
+            .collect(),
+            _ => return vec![start],
+        };
+
+        for date in dates {
+            let occurrence = date.and_time(time);
+            if date > to
+                || until.is_some_and(|until| occurrence > until)
+                || count.is_some_and(|count| occurrences.len() >= count)
+            {
+                return occurrences;
+            }
+            occurrences.push(occurrence);
+        }
+    }
+
+    occurrences
+}
+
+fn parse_weekday(day: &str) -> Option<Weekday> {
+    match day.to_uppercase().as_str() {
+        "MO" => Some(Weekday::Mon),
+        "TU" => Some(Weekday::Tue),
+        "WE" => Some(Weekday::Wed),
+        "TH" => Some(Weekday::Thu),
+        "FR" => Some(Weekday::Fri),
+        "SA" => Some(Weekday::Sat),
+        "SU" => Some(Weekday::Sun),
+        _ => None,
+    }
+}
+
+fn strip_mailto(value: &str) -> &str {
+    value
+        .strip_prefix("mailto:")
+        .or_else(|| value.strip_prefix("MAILTO:"))
+        .unwrap_or(value)
+}
+
+fn unescape(value: &str) -> String {
+    value
+        .replace("\\n", " ")
+        .replace("\\N", " ")
+        .replace("\\,", ",")
+        .replace("\\;", ";")
+        .replace("\\\\", "\\")
+}
@@ -0,0 +1,64 @@
+use std::error::Error;
+
+use crate::moco::client::MocoClient;
+use crate::moco::model::{Project, ProjectTask};
+use crate::utils::{find_project_task, prompt_task_select};
+
+pub mod activitywatch;
+pub mod csv;
+pub mod ics;
+pub mod org;
+pub mod toggl;
+
+/// Resolves the Moco project and task of imported entries, entries without
+/// a usable mapping share one fallback task which is only asked for once
+pub struct TaskMapper<'a> {
+    moco_client: &'a MocoClient,
+    projects: Vec<Project>,
+    project: Option<i64>,
+    task: Option<i64>,
+    fallback: Option<(Project, ProjectTask)>,
+}
+
+impl<'a> TaskMapper<'a> {
+    pub async fn new(
+        moco_client: &'a MocoClient,
+        project: Option<i64>,
+        task: Option<i64>,
+    ) -> Result<TaskMapper<'a>, Box<dyn Error>> {
+        Ok(TaskMapper {
+            moco_client,
+            projects: moco_client.get_assigned_projects().await?,
+            project,
+            task,
+            fallback: None,
+        })
+    }
+
+    pub async fn resolve(
+        &mut self,
+        task_id: Option<i64>,
+        label: &str,
+    ) -> Result<(Project, ProjectTask), Box<dyn Error>> {
+        let mapped = task_id.and_then(|id| find_project_task(&self.projects, id));
+        if let (Some(task_id), None) = (task_id, &mapped) {
+            eprintln!(
+                "Task {} of \"{}\" is not assigned to you, using fallback task",
+                task_id, label
+            );
+        }
+
+        if let Some(mapped) = mapped {
+            return Ok(mapped);
+        }
+
+        if let Some(fallback) = &self.fallback {
+            return Ok(fallback.clone());
+        }
+
+        eprintln!("Select the task for entries without a mapping");
+        let selected = prompt_task_select(self.moco_client, self.project, self.task, None).await?;
+        self.fallback = Some(selected.clone());
+        Ok(selected)
+    }
+}
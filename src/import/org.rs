@@ -0,0 +1,178 @@
+use std::collections::BTreeMap;
+
+use chrono::{NaiveDate, NaiveDateTime};
+
+use crate::utils::stable_hash;
+
+const TODO_KEYWORDS: [&str; 7] = [
+    "TODO",
+    "NEXT",
+    "STARTED",
+    "WAITING",
+    "HOLD",
+    "DONE",
+    "CANCELLED",
+];
+
+/// Clocked time of one heading summed up for one day
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct OrgEntry {
+    pub heading: String,
+    pub tags: Vec<String>,
+    pub moco_task: Option<i64>,
+    pub date: NaiveDate,
+    pub seconds: i64,
+    pub remote_id: String,
+}
+
+struct Heading {
+    level: usize,
+    title: String,
+    tags: Vec<String>,
+    moco_task: Option<i64>,
+}
+
+struct Clock {
+    start: NaiveDateTime,
+    end: NaiveDateTime,
+}
+
+pub fn parse(content: &str) -> Vec<OrgEntry> {
+    let mut stack: Vec<Heading> = vec![];
+    let mut in_properties = false;
+    let mut entries: BTreeMap<(String, NaiveDate), (OrgEntry, Vec<Clock>)> = BTreeMap::new();
+
+    for line in content.lines() {
+        if let Some(heading) = parse_heading(line) {
+            while stack.last().is_some_and(|h| h.level >= heading.level) {
+                stack.pop();
+            }
+            stack.push(heading);
+            in_properties = false;
+            continue;
+        }
+
+        let line = line.trim();
+        if line.eq_ignore_ascii_case(":PROPERTIES:") {
+            in_properties = true;
+        } else if line.eq_ignore_ascii_case(":END:") {
+            in_properties = false;
+        } else if in_properties {
+            if let (Some(value), Some(heading)) =
+                (property_value(line, "MOCO_TASK"), stack.last_mut())
+            {
+                heading.moco_task = value.parse::<i64>().ok();
+            }
+        } else if let (Some(clock), Some(heading)) = (parse_clock(line), stack.last()) {
+            let path = stack
+                .iter()
+                .map(|h| h.title.as_str())
+                .collect::<Vec<&str>>()
+                .join(" / ");
+
+            // Tags and properties are inherited from the parent headings
+            let mut tags: Vec<String> = vec![];
+            for tag in stack.iter().flat_map(|h| h.tags.iter()) {
+                if !tags.contains(tag) {
+                    tags.push(tag.clone());
+                }
+            }
+            let moco_task = stack.iter().rev().find_map(|h| h.moco_task);
+
+            entries
+                .entry((path, clock.start.date()))
+                .or_insert_with(|| {
+                    (
+                        OrgEntry {
+                            heading: heading.title.clone(),
+                            tags,
+                            moco_task,
+                            date: clock.start.date(),
+                            seconds: 0,
+                            remote_id: String::new(),
+                        },
+                        vec![],
+                    )
+                })
+                .1
+                .push(clock);
+        }
+    }
+
+    entries
+        .into_iter()
+        .map(|((path, date), (mut entry, clocks))| {
+            entry.seconds = clocks
+                .iter()
+                .map(|clock| (clock.end - clock.start).num_seconds())
+                .sum();
+            // Keyed by heading and day only, so later CLOCK lines update the activity
+            entry.remote_id = stable_hash(&format!("{}|{}", path, date));
+            entry
+        })
+        .collect()
+}
+
+fn parse_heading(line: &str) -> Option<Heading> {
+    let level = line.chars().take_while(|c| *c == '*').count();
+    if level == 0 || !line[level..].starts_with(' ') {
+        return None;
+    }
+
+    let mut words: Vec<&str> = line[level..].split_whitespace().collect();
+
+    let mut tags = vec![];
+    if let Some(last) = words.last() {
+        if last.len() > 1 && last.starts_with(':') && last.ends_with(':') {
+            tags = last
+                .split(':')
+                .filter(|tag| !tag.is_empty())
+                .map(|tag| tag.to_string())
+                .collect();
+            words.pop();
+        }
+    }
+
+    if words.first().is_some_and(|w| TODO_KEYWORDS.contains(w)) {
+        words.remove(0);
+    }
+    if words
+        .first()
+        .is_some_and(|w| w.starts_with("[#") && w.ends_with(']'))
+    {
+        words.remove(0);
+    }
+
+    Some(Heading {
+        level,
+        title: words.join(" "),
+        tags,
+        moco_task: None,
+    })
+}
+
+fn property_value<'a>(line: &'a str, name: &str) -> Option<&'a str> {
+    let rest = line.strip_prefix(':')?;
+    let (key, value) = rest.split_once(':')?;
+    key.eq_ignore_ascii_case(name).then_some(value.trim())
+}
+
+/// Parses `CLOCK: [2024-05-02 Thu 09:00]--[2024-05-02 Thu 10:30] =>  1:30`,
+/// running clocks without an end are skipped
+fn parse_clock(line: &str) -> Option<Clock> {
+    let rest = line.strip_prefix("CLOCK:")?.trim();
+    let (start, rest) = rest.strip_prefix('[')?.split_once(']')?;
+    let (end, _) = rest.strip_prefix("--[")?.split_once(']')?;
+
+    let start = parse_timestamp(start)?;
+    let end = parse_timestamp(end)?;
+
+    (end > start).then_some(Clock { start, end })
+}
+
+fn parse_timestamp(timestamp: &str) -> Option<NaiveDateTime> {
+    let words: Vec<&str> = timestamp.split_whitespace().collect();
+    let date = NaiveDate::parse_from_str(words.first()?, "%Y-%m-%d").ok()?;
+    let time = chrono::NaiveTime::parse_from_str(words.last()?, "%H:%M").ok()?;
+    Some(date.and_time(time))
+}
@@ -0,0 +1,119 @@
+use std::collections::{BTreeMap, HashMap};
+use std::error::Error;
+
+use chrono::NaiveDate;
+
+use crate::config::TogglRule;
+use crate::utils::stable_hash;
+
+/// Toggl time entries summed up per day, client, project and description
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TogglEntry {
+    pub date: NaiveDate,
+    pub client: String,
+    pub project: String,
+    pub description: String,
+    pub seconds: i64,
+    pub remote_id: String,
+}
+
+/// Parses CSV exports of Toggl's detailed report, entries are summed up
+/// across all files
+pub fn parse(contents: &[String]) -> Result<Vec<TogglEntry>, Box<dyn Error>> {
+    let mut entries: BTreeMap<(NaiveDate, String, String, String), i64> = BTreeMap::new();
+    for content in contents {
+        parse_file(content, &mut entries)?;
+    }
+
+    Ok(entries
+        .into_iter()
+        .map(
+            |((date, client, project, description), seconds)| TogglEntry {
+                remote_id: stable_hash(&format!("{}|{}|{}|{}", date, client, project, description)),
+                date,
+                client,
+                project,
+                description,
+                seconds,
+            },
+        )
+        .collect())
+}
+
+fn parse_file(
+    content: &str,
+    entries: &mut BTreeMap<(NaiveDate, String, String, String), i64>,
+) -> Result<(), Box<dyn Error>> {
+    let mut reader = ::csv::ReaderBuilder::new()
+        .flexible(true)
+        .from_reader(content.as_bytes());
+
+    let headers: HashMap<String, usize> = reader
+        .headers()?
+        .iter()
+        .enumerate()
+        .map(|(index, header)| (header.trim().to_lowercase(), index))
+        .collect();
+
+    for column in ["start date", "duration"] {
+        if !headers.contains_key(column) {
+            return Err(format!(
+                "Column \"{}\" is missing, is this a Toggl detailed report?",
+                column
+            )
+            .into());
+        }
+    }
+
+    for record in reader.records() {
+        let record = record?;
+        let field = |column: &str| {
+            headers
+                .get(column)
+                .and_then(|index| record.get(*index))
+                .map(|value| value.trim().to_string())
+                .unwrap_or_default()
+        };
+
+        let date = NaiveDate::parse_from_str(&field("start date"), "%Y-%m-%d")?;
+        let seconds = parse_duration(&field("duration"))
+            .ok_or(format!("Invalid duration \"{}\"", field("duration")))?;
+
+        *entries
+            .entry((
+                date,
+                field("client"),
+                field("project"),
+                field("description"),
+            ))
+            .or_insert(0) += seconds;
+    }
+
+    Ok(())
+}
+
+pub fn find_task(rules: &[TogglRule], entry: &TogglEntry) -> Option<i64> {
+    rules
+        .iter()
+        .find(|rule| {
+            rule.client
+                .as_ref()
+                .is_none_or(|client| client.eq_ignore_ascii_case(&entry.client))
+                && rule
+                    .project
+                    .as_ref()
+                    .is_none_or(|project| project.eq_ignore_ascii_case(&entry.project))
+        })
+        .map(|rule| rule.task)
+}
+
+/// Parses durations in the format `HH:MM:SS`
+fn parse_duration(duration: &str) -> Option<i64> {
+    let mut parts = duration.split(':').map(|part| part.parse::<i64>().ok());
+    match (parts.next(), parts.next(), parts.next(), parts.next()) {
+        (Some(Some(hours)), Some(Some(minutes)), Some(Some(seconds)), None) => {
+            Some(hours * 60 * 60 + minutes * 60 + seconds)
+        }
+        _ => None,
+    }
+}
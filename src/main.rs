@@ -1,30 +1,47 @@
-use std::collections::HashMap;
-
 use std::io::Write;
 use std::process::exit;
 use std::{error::Error, sync::Arc, vec};
 
-use chrono::{NaiveDate, Utc};
+use chrono::{Datelike, Months, NaiveDate, Utc};
 use log::{log_enabled, trace};
 
 use jira_tempo::client::JiraTempoClient;
 
+use import::TaskMapper;
+use timer::local::LocalTimer;
+use timer::status::StatusCache;
 use tokio::sync::RwLock;
-use utils::{prompt_activity_select, prompt_task_select, render_table};
+use utils::{
+    find_project_task, print_info, prompt_activity_select, prompt_task_select, render_output,
+    render_table, sync_activities,
+};
 
-use crate::moco::model::{ControlActivityTimer, CreateActivity, DeleteActivity, GetActivity};
+use crate::duration::Duration;
+use crate::moco::model::{
+    Activity, CreateActivity, CreateSchedule, DeleteActivity, GetActivity, Schedule,
+    ABSENCE_VACATION,
+};
 use crate::{
     moco::{client::MocoClient, model::EditActivity},
     utils::{ask_question, mandatory_validator, optional_validator},
 };
 
+mod balance;
 mod cli;
 mod config;
+mod duration;
+mod export;
+mod import;
 mod jira_tempo;
 mod moco;
+mod period;
+mod presence;
+mod report;
 mod tempo;
+mod timer;
 
 mod utils;
+mod worktime;
 
 #[tokio::main]
 async fn main() -> Result<(), Box<dyn Error>> {
@@ -37,22 +54,25 @@ async fn main() -> Result<(), Box<dyn Error>> {
     }
     log_builder.init();
 
+    if args.no_input {
+        utils::disable_input();
+    }
+
     let config = Arc::new(RwLock::new(config::init()?));
     let moco_client = MocoClient::new(&config, args.debug)?;
     let tempo_client = JiraTempoClient::new(&config, args.debug)?;
 
+    let duration_format = config.read().await.duration_format();
+
     match args.command {
         cli::Commands::Login { system: _ } => {}
         cli::Commands::Sync {
             system: _,
-            today: _,
-            week: _,
-            last_week: _,
-            month: _,
-            last_month: _,
+            period: _,
             project: _,
             task: _,
             dry_run: _,
+            output: _,
         } => {
             if !config.read().await.has_jira_credetials() {
                 println!("Please login to Jira with the \"login jira\" command first");
@@ -72,6 +92,24 @@ async fn main() -> Result<(), Box<dyn Error>> {
         }
     }
 
+    let fixing_timer = matches!(
+        args.command,
+        cli::Commands::Timer {
+            system: cli::Timer::Fix,
+            ..
+        }
+    );
+    // Status bars redraw often and must not call the API every time
+    let status_bar = matches!(args.command, cli::Commands::Timer { bar: Some(_), .. });
+    if config.read().await.has_moco_credentials() && !status_bar {
+        if let Err(e) = timer::local::deliver_queue(&moco_client).await {
+            log::debug!("Could not deliver queued bookings: {}", e);
+        }
+        if !fixing_timer {
+            timer::warn_forgotten(&moco_client, &*config.read().await).await;
+        }
+    }
+
     match args.command {
         cli::Commands::Login { system } => match system {
             cli::Login::Jira => {
@@ -107,23 +145,40 @@ async fn main() -> Result<(), Box<dyn Error>> {
             }
         },
         cli::Commands::List {
-            today,
-            week,
-            last_week,
-            month,
-            last_month,
+            period,
             compact,
+            group_by,
+            project,
+            task,
+            customer,
+            billable,
+            not_billable,
+            billed,
+            synced_from,
+            search,
+            details,
+            output: cli::OutputArgs { output },
         } => {
-            let (from, to) = utils::select_from_to_date(today, week, last_week, month, last_month);
+            let (from, to) = period.resolve(&*config.read().await)?;
+
+            let filter = report::ActivityFilter {
+                project,
+                task,
+                customer,
+                billable: (billable || not_billable).then_some(billable),
+                billed: billed.then_some(true),
+                remote_service: synced_from,
+                search,
+            };
 
             let mut activities = moco_client
-                .get_activities(
+                .query_activities(
                     from.format("%Y-%m-%d").to_string(),
                     to.format("%Y-%m-%d").to_string(),
-                    None,
-                    None,
+                    &filter.query(),
                 )
                 .await?;
+            activities.retain(|activity| filter.matches(activity));
 
             if log_enabled!(log::Level::Trace) {
                 trace!("{:#?}", activities);
@@ -135,91 +190,117 @@ async fn main() -> Result<(), Box<dyn Error>> {
                     .cmp(&NaiveDate::parse_from_str(b.date.as_str(), "%Y-%m-%d").unwrap())
             });
 
-            let hours_sum = activities
-                .iter()
-                .fold(0.0, |hours, activity| activity.hours + hours)
-                .to_string();
-
-            let mut list: Vec<Vec<String>> = vec![];
-
-            if compact {
-                list.push(vec!["Date".to_string(), "Duration (hours)".to_string()]);
-                let mut activities_peer_day: Vec<Vec<String>> = activities
-                    .iter()
-                    .fold(HashMap::new(), |mut acc: HashMap<&str, f64>, activity| {
-                        let date = activity.date.as_str();
-                        let hours = activity.hours;
-                        acc.insert(date, acc.get(date).map_or(hours, |x| hours + x));
-                        acc
-                    })
-                    .iter()
-                    .map(|(date, hours)| vec![date.to_string(), format!("{}", hours)])
-                    .collect();
-
-                activities_peer_day.sort_by(|a, b| {
-                    NaiveDate::parse_from_str(a[0].as_str(), "%Y-%m-%d")
-                        .unwrap()
-                        .cmp(&NaiveDate::parse_from_str(b[0].as_str(), "%Y-%m-%d").unwrap())
-                });
-
-                list.append(&mut activities_peer_day);
+            let group_by = if group_by.is_empty() && compact {
+                vec![cli::GroupBy::Date]
+            } else {
+                group_by
+            };
 
-                list.push(vec!["-".to_string(), hours_sum]);
+            if !group_by.is_empty() {
+                let mut list = report::group_activities(
+                    &activities,
+                    &group_by,
+                    compact,
+                    duration_format,
+                    config.read().await.week_start(),
+                );
+                if compact && group_by == [cli::GroupBy::Date] {
+                    let presences = moco_client
+                        .get_presences(
+                            from.format("%Y-%m-%d").to_string(),
+                            to.format("%Y-%m-%d").to_string(),
+                        )
+                        .await?;
+                    let config = config.read().await;
+                    let totals = presence::totals_by_date(
+                        &presences,
+                        config.today(),
+                        presence::now(&config),
+                    );
+                    report::add_presence(&mut list, &totals, duration_format);
+                }
+                render_output(
+                    output,
+                    list.header,
+                    list.rows,
+                    Some(list.footer),
+                    &list.records,
+                )?;
             } else {
-                list.push(vec![
+                let mut header = vec![
                     "Date".to_string(),
-                    "Duration (hours)".to_string(),
+                    "Duration".to_string(),
                     "Customer".to_string(),
                     "Task".to_string(),
                     "Description".to_string(),
-                ]);
-
-                let mut mapped_activities: Vec<Vec<String>> = activities
-                    .iter()
-                    .map(|activity| {
-                        vec![
-                            activity.date.clone(),
-                            activity.hours.to_string(),
-                            activity.customer.name.clone(),
-                            activity.task.name.clone(),
-                            activity
-                                .description
-                                .as_ref()
-                                .unwrap_or(&String::new())
-                                .to_string(),
-                        ]
-                    })
-                    .collect();
-
-                list.append(&mut mapped_activities);
-
-                list.push(vec![
+                ];
+                let mut footer = vec![
                     "-".to_string(),
                     activities
                         .iter()
-                        .fold(0.0, |hours, activity| activity.hours + hours)
-                        .to_string(),
+                        .map(Activity::duration)
+                        .sum::<Duration>()
+                        .format(duration_format),
                     "-".to_string(),
                     "-".to_string(),
                     "".to_string(),
-                ]);
-            }
+                ];
+                if details {
+                    header.extend(["Tag", "Billable", "Remote"].map(str::to_string));
+                    footer.extend(["", "", ""].map(str::to_string));
+                }
 
-            render_table(list);
+                render_output(
+                    output,
+                    header,
+                    activities
+                        .iter()
+                        .map(|activity| {
+                            let mut row = vec![
+                                activity.date.clone(),
+                                activity.duration().format(duration_format),
+                                activity.customer.name.clone(),
+                                activity.task.name.clone(),
+                                activity
+                                    .description
+                                    .as_ref()
+                                    .unwrap_or(&String::new())
+                                    .to_string(),
+                            ];
+                            if details {
+                                row.extend([
+                                    activity.tag.clone(),
+                                    if activity.billable { "yes" } else { "no" }.to_string(),
+                                    utils::remote_link(activity),
+                                ]);
+                            }
+                            row
+                        })
+                        .collect(),
+                    Some(footer),
+                    &activities,
+                )?;
+            }
         }
         cli::Commands::New {
             project,
             task,
             hours,
+            start,
+            end,
             date,
             description,
+            fields,
         } => {
-            let now = Utc::now().format("%Y-%m-%d").to_string();
+            let now = config.read().await.today().format("%Y-%m-%d").to_string();
+            let (remote_service, remote_url) = fields.remote(&*config.read().await)?;
 
-            let (project, task) = prompt_task_select(&moco_client, project, task).await?;
+            let (project, task) = prompt_task_select(&moco_client, project, task, None).await?;
 
             let date = if let Some(d) = date {
                 d
+            } else if !utils::input_enabled() {
+                now
             } else {
                 print!("Date (YYYY-MM-DD) - Default 'today': ");
                 std::io::stdout().flush()?;
@@ -232,84 +313,211 @@ async fn main() -> Result<(), Box<dyn Error>> {
                 }
             };
 
-            let hours = if let Some(h) = hours {
+            let duration = if let Some(h) = hours {
                 h
+            } else if let (Some(start), Some(end)) = (start, end) {
+                Duration::between(start, end)?
+            } else if !utils::input_enabled() {
+                Duration::default()
             } else {
-                let answer =
-                    ask_question("Duration (hours) - Default 'start timer': ", &|answer| {
-                        answer.is_empty().then_some(None).unwrap_or_else(|| {
-                            answer.parse::<f64>().err().map(|e| format!("{}", e))
-                        })
-                    })?;
-                answer
-                    .is_empty()
-                    .then_some(0_f64)
-                    .unwrap_or_else(|| answer.parse::<f64>().unwrap())
+                let answer = ask_question("Duration - Default 'start timer': ", &|answer| {
+                    if answer.is_empty() {
+                        None
+                    } else {
+                        answer.parse::<Duration>().err()
+                    }
+                })?;
+                if answer.is_empty() {
+                    Duration::default()
+                } else {
+                    answer.parse::<Duration>()?
+                }
             };
 
             let description = if let Some(d) = description {
                 d
+            } else if !utils::input_enabled() {
+                String::new()
             } else {
                 ask_question("Description: ", &optional_validator)?
             };
 
+            let rounded = config.read().await.round(duration);
+            if rounded != duration {
+                println!(
+                    "Duration {} rounded to {}",
+                    duration.format(duration_format),
+                    rounded.format(duration_format)
+                );
+            }
+
             moco_client
                 .create_activity(&CreateActivity {
                     date,
                     project_id: project.id,
                     task_id: task.id,
-                    hours: Some(hours),
+                    seconds: Some(rounded.seconds()),
                     description,
+                    billable: fields.billable(),
+                    tag: fields.tag,
+                    remote_service,
+                    remote_id: fields.remote_id,
+                    remote_url,
                     ..Default::default()
                 })
                 .await?;
         }
-        cli::Commands::Edit { activity } => {
-            let activity = prompt_activity_select(&moco_client, activity).await?;
-
-            let now = Utc::now().format("%Y-%m-%d").to_string();
+        cli::Commands::Edit {
+            activity,
+            project,
+            task,
+            hours,
+            date,
+            description,
+            fields,
+        } => {
+            let (remote_service, remote_url) = fields.remote(&*config.read().await)?;
+            // Given fields make the edit non-interactive, omitted fields keep their values
+            let interactive = project.is_none()
+                && task.is_none()
+                && hours.is_none()
+                && date.is_none()
+                && description.is_none()
+                && fields.is_empty();
+            if interactive {
+                utils::require_input("--project, --task, --hours, --date or --description")?;
+            }
 
-            print!("New date (YYYY-MM-DD) - Default '{}': ", activity.date);
-            std::io::stdout().flush()?;
+            let activity = prompt_activity_select(
+                &moco_client,
+                activity,
+                config.read().await.today(),
+                duration_format,
+            )
+            .await?;
+            let current = (activity.project.id, activity.task.id);
+
+            let (project_id, task_id) = if interactive {
+                let (project, task) =
+                    prompt_task_select(&moco_client, None, None, Some(current)).await?;
+                (project.id, task.id)
+            } else if let (None, Some(task_id)) = (project, task) {
+                // A task of another project moves the activity to that project
+                let projects = moco_client.get_assigned_projects().await?;
+                match find_project_task(&projects, task_id) {
+                    Some((project, task)) => (project.id, task.id),
+                    None => {
+                        println!("Task {} is not assigned to you", task_id);
+                        exit(1);
+                    }
+                }
+            } else if project.is_some() {
+                let (project, task) = prompt_task_select(
+                    &moco_client,
+                    project.or(Some(current.0)),
+                    task.or(Some(current.1)),
+                    Some(current),
+                )
+                .await?;
+                (project.id, task.id)
+            } else {
+                current
+            };
 
-            let mut date = utils::read_line()?;
-            if date.is_empty() {
-                date = now.clone()
-            }
+            let date = match date {
+                Some(date) => date,
+                None if interactive => {
+                    print!("New date (YYYY-MM-DD) - Default '{}': ", activity.date);
+                    std::io::stdout().flush()?;
 
-            print!("New duration (hours) - Default '{}': ", activity.hours);
-            std::io::stdout().flush()?;
+                    let date = utils::read_line()?;
+                    if date.is_empty() {
+                        activity.date.clone()
+                    } else {
+                        date
+                    }
+                }
+                None => activity.date.clone(),
+            };
 
-            let mut hours = utils::read_line()?;
-            if hours.is_empty() {
-                hours = activity.hours.to_string()
-            }
+            let duration = match hours {
+                Some(hours) => hours,
+                None if interactive => {
+                    let answer = ask_question(
+                        &format!(
+                            "New duration - Default '{}': ",
+                            activity.duration().format(duration_format)
+                        ),
+                        &|answer| {
+                            if answer.is_empty() {
+                                None
+                            } else {
+                                answer.parse::<Duration>().err()
+                            }
+                        },
+                    )?;
+                    if answer.is_empty() {
+                        activity.duration()
+                    } else {
+                        answer.parse::<Duration>()?
+                    }
+                }
+                None => activity.duration(),
+            };
 
-            print!("New description - Default 'current': ");
-            std::io::stdout().flush()?;
+            let current_description = activity.description.clone().unwrap_or_default();
+            let description = match description {
+                Some(description) => description,
+                None if interactive => {
+                    print!("New description - Default '{}': ", current_description);
+                    std::io::stdout().flush()?;
 
-            let mut description = utils::read_line()?;
-            if description.is_empty() {
-                description = activity
-                    .description
-                    .as_ref()
-                    .unwrap_or(&String::new())
-                    .to_string()
-            }
+                    let description = utils::read_line()?;
+                    if description.is_empty() {
+                        current_description
+                    } else {
+                        description
+                    }
+                }
+                None => current_description,
+            };
 
             moco_client
                 .edit_activity(&EditActivity {
                     activity_id: activity.id,
-                    project_id: activity.project.id,
-                    task_id: activity.task.id,
+                    project_id,
+                    task_id,
                     date,
                     description,
-                    hours,
+                    seconds: duration.seconds(),
+                    billable: fields.billable(),
+                    tag: fields.tag,
+                    remote_service,
+                    remote_id: fields.remote_id,
+                    remote_url,
                 })
                 .await?;
         }
         cli::Commands::Rm { activity } => {
-            let activity = prompt_activity_select(&moco_client, activity).await?;
+            let activity = prompt_activity_select(
+                &moco_client,
+                activity,
+                config.read().await.today(),
+                duration_format,
+            )
+            .await?;
+
+            let question = format!(
+                "Delete activity {} of {} ({}, {})?",
+                activity.id,
+                activity.date,
+                activity.task.name,
+                activity.duration().format(duration_format)
+            );
+            if !utils::confirm(&question, args.yes)? {
+                println!("Aborted");
+                return Ok(());
+            }
 
             moco_client
                 .delete_activity(&DeleteActivity {
@@ -317,56 +525,783 @@ async fn main() -> Result<(), Box<dyn Error>> {
                 })
                 .await?;
         }
-        cli::Commands::Timer { system, activity } => match system {
-            cli::Timer::Start => {
-                let activity = prompt_activity_select(&moco_client, activity).await?;
-
-                moco_client
-                    .control_activity_timer(&ControlActivityTimer {
-                        control: "start".to_string(),
-                        activity_id: activity.id,
-                    })
+        cli::Commands::Timer {
+            system,
+            activity,
+            project,
+            task,
+            description,
+            local,
+            note,
+            previous,
+            bar,
+            hours,
+            format,
+        } => {
+            let local = activity.is_none()
+                && (local || config.read().await.local_timer.unwrap_or_default());
+
+            match system {
+                cli::Timer::Start if local => {
+                    if LocalTimer::load()?.is_some() {
+                        println!("A local timer is already running, stop it first");
+                        exit(1);
+                    }
+
+                    let today = config.read().await.today();
+                    timer::local::select(&moco_client, project, task, description, today)
+                        .await?
+                        .save()?;
+                    println!("Started local timer");
+                    presence::clock_in_on_first_timer(&moco_client, &*config.read().await).await;
+                }
+                cli::Timer::Start => {
+                    let activity = timer::select_activity(
+                        &moco_client,
+                        activity,
+                        project,
+                        task,
+                        description,
+                        config.read().await.today(),
+                        duration_format,
+                    )
                     .await?;
-            }
-            cli::Timer::Stop => {
-                let now = Utc::now().format("%Y-%m-%d").to_string();
-                let from = now.clone();
-                let to = now.clone();
-
-                let activities = moco_client.get_activities(from, to, None, None).await?;
-                let activity = activities.iter().find(|a| !a.timer_started_at.is_null());
-
-                if let Some(a) = activity {
-                    moco_client
-                        .control_activity_timer(&ControlActivityTimer {
-                            control: "stop".to_string(),
-                            activity_id: a.id,
-                        })
+
+                    timer::start(&moco_client, &activity).await?;
+                    presence::clock_in_on_first_timer(&moco_client, &*config.read().await).await;
+                }
+                cli::Timer::Stop => {
+                    if let Some(local_timer) = LocalTimer::load()? {
+                        timer::local::stop(&moco_client, local_timer, &*config.read().await)
+                            .await?;
+                        return Ok(());
+                    }
+
+                    let running =
+                        timer::find_running(&moco_client, config.read().await.today()).await?;
+
+                    if let Some(running) = running {
+                        timer::stop(&moco_client, &running.activity, &*config.read().await).await?;
+                    } else {
+                        println!("Could not stop timer since it was not on");
+                    }
+                }
+                cli::Timer::Switch => {
+                    let today = config.read().await.today();
+                    let (project, task, description) = if previous {
+                        let Some(previous) = StatusCache::load().previous else {
+                            println!("No timer was stopped yet");
+                            exit(1);
+                        };
+                        (
+                            Some(previous.project.id),
+                            Some(previous.task.id),
+                            Some(previous.description.unwrap_or_default()),
+                        )
+                    } else {
+                        (project, task, description)
+                    };
+                    // Select first so there is no gap between the two timers
+                    let (next_local, next_activity) = if local {
+                        let next =
+                            timer::local::select(&moco_client, project, task, description, today)
+                                .await?;
+                        (Some(next), None)
+                    } else {
+                        let next = timer::select_activity(
+                            &moco_client,
+                            activity,
+                            project,
+                            task,
+                            description,
+                            today,
+                            duration_format,
+                        )
                         .await?;
+                        (None, Some(next))
+                    };
+
+                    let mut stopped = false;
+                    if let Some(local_timer) = LocalTimer::load()? {
+                        timer::local::stop(&moco_client, local_timer, &*config.read().await)
+                            .await?;
+                        stopped = true;
+                    }
+
+                    let running = match timer::find_running(&moco_client, today).await {
+                        Ok(running) => running,
+                        Err(e) if timer::local::is_offline(e.as_ref()) && local => None,
+                        Err(e) => return Err(e),
+                    };
+                    match (running, &next_activity) {
+                        (Some(running), Some(next)) if running.activity.id == next.id => {
+                            println!("Timer of activity {} is already running", next.id);
+                            return Ok(());
+                        }
+                        (Some(running), _) => {
+                            timer::stop(&moco_client, &running.activity, &*config.read().await)
+                                .await?;
+                        }
+                        (None, _) if !stopped => println!("No timer was running"),
+                        (None, _) => {}
+                    }
+
+                    if let Some(next) = next_local {
+                        next.save()?;
+                        println!("Started local timer");
+                    }
+                    if let Some(next) = next_activity {
+                        timer::start(&moco_client, &next).await?;
+                        println!("Started timer of activity {}", next.id);
+                    }
+                    presence::clock_in_on_first_timer(&moco_client, &*config.read().await).await;
+                }
+                cli::Timer::Fix => {
+                    let running =
+                        timer::find_running(&moco_client, config.read().await.today()).await?;
+
+                    if let Some(running) = running {
+                        timer::fix(&moco_client, &running, hours, &*config.read().await).await?;
+                    } else {
+                        println!("No timer running");
+                    }
+                }
+                cli::Timer::Pause | cli::Timer::Resume | cli::Timer::Annotate => {
+                    let Some(mut local_timer) = LocalTimer::load()? else {
+                        println!("No local timer running, Moco timers can only be stopped");
+                        exit(1);
+                    };
+
+                    match system {
+                        cli::Timer::Pause => {
+                            local_timer.pause(Utc::now());
+                            println!(
+                                "Paused after {}",
+                                local_timer.elapsed(Utc::now()).format(duration_format)
+                            );
+                        }
+                        cli::Timer::Resume => {
+                            local_timer.resume(Utc::now());
+                            println!("Resumed");
+                        }
+                        _ => {
+                            let note = match note {
+                                Some(note) => note,
+                                None => {
+                                    utils::require_input("--note")?;
+                                    ask_question("Note: ", &mandatory_validator)?
+                                }
+                            };
+                            local_timer.notes.push(note);
+                        }
+                    }
+                    local_timer.save()?;
+                }
+                cli::Timer::Status => {
+                    let (today, timezone) = {
+                        let config = config.read().await;
+                        (config.today(), config.timezone())
+                    };
+
+                    // i3blocks passes clicks on the block as environment variable
+                    if bar == Some(cli::Bar::I3blocks) {
+                        let action = match std::env::var("BLOCK_BUTTON").as_deref() {
+                            Ok("1") => Some(vec!["timer", "stop"]),
+                            Ok("3") => Some(vec!["timer", "switch", "--previous", "--no-input"]),
+                            _ => None,
+                        };
+                        if let Some(action) = action {
+                            std::process::Command::new(std::env::current_exe()?)
+                                .args(action)
+                                .env_remove("BLOCK_BUTTON")
+                                .stdout(std::process::Stdio::null())
+                                .status()?;
+                        }
+                    }
+
+                    let local_timer = LocalTimer::load()?;
+                    let running = match (&local_timer, bar) {
+                        (Some(_), _) => None,
+                        (None, Some(_)) => {
+                            timer::status::cached_running(&moco_client, &*config.read().await)
+                                .await?
+                        }
+                        (None, None) => timer::find_running(&moco_client, today).await?,
+                    };
+                    let render = |template: &str| match (&local_timer, &running) {
+                        (Some(local_timer), _) => {
+                            local_timer.render(template, &timezone, today, duration_format)
+                        }
+                        (None, Some(running)) => {
+                            running.render(template, &timezone, today, duration_format)
+                        }
+                        (None, None) => String::new(),
+                    };
+
+                    let stopped = local_timer.is_none() && running.is_none();
+                    if let Some(bar) = bar {
+                        let text = (!stopped).then(|| {
+                            render(format.as_deref().unwrap_or("{project} / {task} {elapsed}"))
+                        });
+                        let tooltip = (!stopped).then(|| {
+                            render("{project} / {task}\n{description}\nStarted {start}, {state}")
+                        });
+                        println!(
+                            "{}",
+                            timer::status::render(bar, text, tooltip, local_timer.as_ref())
+                        );
+                        if stopped && bar == cli::Bar::Plain {
+                            exit(1);
+                        }
+                        return Ok(());
+                    }
+
+                    if stopped {
+                        if format.is_none() {
+                            println!("No timer running");
+                        }
+                        exit(1);
+                    }
+
+                    match format {
+                        Some(template) => println!("{}", render(&template)),
+                        None => render_table(
+                            [
+                                ("Project", "{project}"),
+                                ("Task", "{task}"),
+                                ("Description", "{description}"),
+                                ("Started", "{start}"),
+                                ("Elapsed", "{elapsed}"),
+                                ("State", "{state}"),
+                            ]
+                            .iter()
+                            .map(|(label, template)| vec![label.to_string(), render(template)])
+                            .collect(),
+                        ),
+                    }
+                }
+            }
+        }
+        cli::Commands::Presence {
+            system,
+            period,
+            output: cli::OutputArgs { output },
+        } => {
+            let config = config.read().await;
+            match system {
+                cli::Presence::In => {
+                    let presences = presence::today(&moco_client, &config).await?;
+                    if let Some(open) = presences.iter().find(|p| p.is_open()) {
+                        println!("Already clocked in since {}", open.from);
+                    } else {
+                        let presence = moco_client.touch_presence().await?;
+                        println!("Clocked in at {}", presence.from);
+                    }
+                }
+                cli::Presence::Out => {
+                    let presences = presence::today(&moco_client, &config).await?;
+                    if !presences.iter().any(|p| p.is_open()) {
+                        println!("Not clocked in");
+                        exit(1);
+                    }
+
+                    let warn = config
+                        .worktime
+                        .as_ref()
+                        .and_then(|worktime| worktime.warn_on_clock_out)
+                        .unwrap_or_default();
+                    if warn {
+                        let rules = worktime::Rules::new(&config);
+                        let today = config.today().format("%Y-%m-%d").to_string();
+                        let days = worktime::days(&presences, &[], &today, presence::now(&config));
+                        let violations: Vec<worktime::Violation> = days
+                            .iter()
+                            .flat_map(|day| worktime::check(&rules, day, duration_format))
+                            .collect();
+                        for violation in &violations {
+                            println!(
+                                "Warning: {} is {}, the limit is {}",
+                                violation.rule,
+                                violation.actual.format(duration_format),
+                                violation.limit.format(duration_format)
+                            );
+                        }
+                        if !violations.is_empty() && !utils::confirm("Clock out anyway?", args.yes)?
+                        {
+                            exit(1);
+                        }
+                    }
 
-                    let a = moco_client
-                        .get_activity(&GetActivity { activity_id: a.id })
+                    let closed = moco_client.touch_presence().await?;
+                    let presences = presence::today(&moco_client, &config).await?;
+                    let total: Duration = presence::totals_by_date(
+                        &presences,
+                        config.today(),
+                        presence::now(&config),
+                    )
+                    .into_values()
+                    .sum();
+                    println!(
+                        "Clocked out at {}, {} today",
+                        closed.to.unwrap_or_default(),
+                        total.format(duration_format)
+                    );
+                }
+                cli::Presence::Status => {
+                    let presences = presence::today(&moco_client, &config).await?;
+                    let total: Duration = presence::totals_by_date(
+                        &presences,
+                        config.today(),
+                        presence::now(&config),
+                    )
+                    .into_values()
+                    .sum();
+                    match presences.iter().find(|p| p.is_open()) {
+                        Some(open) => println!(
+                            "Clocked in since {}, {} today",
+                            open.from,
+                            total.format(duration_format)
+                        ),
+                        None => println!("Clocked out, {} today", total.format(duration_format)),
+                    }
+                }
+                cli::Presence::List => {
+                    let (from, to) = period.resolve(&config)?;
+                    let mut presences = moco_client
+                        .get_presences(
+                            from.format("%Y-%m-%d").to_string(),
+                            to.format("%Y-%m-%d").to_string(),
+                        )
                         .await?;
-                    println!("Activity duration: {} hours", a.hours);
-                } else {
-                    println!("Could not stop timer since it was not on");
+                    presences.sort_by(|a, b| (&a.date, &a.from).cmp(&(&b.date, &b.from)));
+
+                    let today = config.today().format("%Y-%m-%d").to_string();
+                    let now = presence::now(&config);
+                    let duration = |presence: &moco::model::Presence| {
+                        presence.duration((presence.date == today).then_some(now))
+                    };
+                    render_output(
+                        output,
+                        vec![
+                            "Date".to_string(),
+                            "From".to_string(),
+                            "To".to_string(),
+                            "Duration".to_string(),
+                        ],
+                        presences
+                            .iter()
+                            .map(|presence| {
+                                vec![
+                                    presence.date.clone(),
+                                    presence.from.clone(),
+                                    presence.to.clone().unwrap_or_default(),
+                                    duration(presence).format(duration_format),
+                                ]
+                            })
+                            .collect(),
+                        Some(vec![
+                            "-".to_string(),
+                            "-".to_string(),
+                            "-".to_string(),
+                            presences
+                                .iter()
+                                .map(duration)
+                                .sum::<Duration>()
+                                .format(duration_format),
+                        ]),
+                        &presences,
+                    )?;
                 }
             }
-        },
+        }
+        cli::Commands::Absence {
+            system: cli::Absence::Add,
+            absence_type,
+            half,
+            comment,
+            mut period,
+            output: _,
+        } => {
+            if period.is_empty() {
+                println!("Please give the days of the absence with --from (and --to) or --period");
+                exit(1);
+            }
+            if period.to.is_none() && period.period.is_none() {
+                period.to = period.from.clone();
+            }
+            let (from, to) = period.resolve(&*config.read().await)?;
+
+            // Days with an absence like a public holiday are skipped
+            let booked: Vec<Schedule> = moco_client
+                .get_schedules(
+                    from.format("%Y-%m-%d").to_string(),
+                    to.format("%Y-%m-%d").to_string(),
+                    None,
+                )
+                .await?
+                .into_iter()
+                .filter(|schedule| schedule.absence_code().is_some())
+                .collect();
+            for schedule in &booked {
+                println!(
+                    "Skipping {}, it is already booked as {}",
+                    schedule.date, schedule.assignment.name
+                );
+            }
+
+            let dates: Vec<String> = from
+                .iter_days()
+                .take_while(|date| *date <= to)
+                .filter(|date| date.weekday().number_from_monday() < 6)
+                .map(|date| date.format("%Y-%m-%d").to_string())
+                .filter(|date| !booked.iter().any(|schedule| schedule.date == *date))
+                .collect();
+            if dates.is_empty() {
+                println!("There are no free weekdays from {} to {}", from, to);
+                exit(1);
+            }
+
+            let question = format!(
+                "Add {} on {} days from {} to {}?",
+                absence_type.name(),
+                dates.len(),
+                dates[0],
+                dates[dates.len() - 1]
+            );
+            if !utils::confirm(&question, args.yes)? {
+                println!("Aborted");
+                return Ok(());
+            }
+
+            let mut created = vec![];
+            for date in &dates {
+                let schedule = moco_client
+                    .create_schedule(&CreateSchedule {
+                        date: date.clone(),
+                        absence_code: absence_type.code(),
+                        am: half != Some(cli::HalfDay::Pm),
+                        pm: half != Some(cli::HalfDay::Am),
+                        comment: comment.clone(),
+                    })
+                    .await;
+                match schedule {
+                    Ok(schedule) => {
+                        println!("Added {} on {}", schedule.assignment.name, schedule.date);
+                        created.push(date.clone());
+                    }
+                    Err(e) => {
+                        println!("Could not add the absence on {}: {}", date, e);
+                        if created.is_empty() {
+                            println!("Nothing was added");
+                        } else {
+                            println!("Already added: {}", created.join(", "));
+                        }
+                        exit(1);
+                    }
+                }
+            }
+        }
+        cli::Commands::Absence {
+            system: cli::Absence::List,
+            absence_type: _,
+            half: _,
+            comment: _,
+            mut period,
+            output: cli::OutputArgs { output },
+        } => {
+            if period.is_empty() {
+                period.period = Some("this year".to_string());
+            }
+            let (from, to) = period.resolve(&*config.read().await)?;
+            let mut absences: Vec<Schedule> = moco_client
+                .get_schedules(
+                    from.format("%Y-%m-%d").to_string(),
+                    to.format("%Y-%m-%d").to_string(),
+                    None,
+                )
+                .await?
+                .into_iter()
+                .filter(|schedule| schedule.absence_code().is_some())
+                .collect();
+            absences.sort_by(|a, b| a.date.cmp(&b.date));
+
+            render_output(
+                output,
+                vec![
+                    "Date".to_string(),
+                    "Type".to_string(),
+                    "Days".to_string(),
+                    "Comment".to_string(),
+                ],
+                absences
+                    .iter()
+                    .map(|absence| {
+                        vec![
+                            absence.date.clone(),
+                            absence.assignment.name.clone(),
+                            absence.days().to_string(),
+                            absence.comment.clone().unwrap_or_default(),
+                        ]
+                    })
+                    .collect(),
+                Some(vec![
+                    "-".to_string(),
+                    "-".to_string(),
+                    absences.iter().map(Schedule::days).sum::<f64>().to_string(),
+                    "".to_string(),
+                ]),
+                &absences,
+            )?;
+        }
+        cli::Commands::Vacation {
+            system: cli::Vacation::Balance,
+            year,
+            output: cli::OutputArgs { output },
+        } => {
+            let today = config.read().await.today();
+            let year = year.unwrap_or(today.year());
+            let entitlement: f64 = moco_client
+                .get_user_holidays(year)
+                .await?
+                .iter()
+                .map(|holiday| holiday.days)
+                .sum();
+            let vacation = moco_client
+                .get_schedules(
+                    format!("{}-01-01", year),
+                    format!("{}-12-31", year),
+                    Some(ABSENCE_VACATION),
+                )
+                .await?;
+            let today = today.format("%Y-%m-%d").to_string();
+            let (taken, planned): (Vec<&Schedule>, Vec<&Schedule>) =
+                vacation.iter().partition(|schedule| schedule.date <= today);
+            let taken: f64 = taken.into_iter().map(Schedule::days).sum();
+            let planned: f64 = planned.into_iter().map(Schedule::days).sum();
+            let remaining = entitlement - taken - planned;
+
+            render_output(
+                output,
+                vec![
+                    "Year".to_string(),
+                    "Entitlement".to_string(),
+                    "Taken".to_string(),
+                    "Planned".to_string(),
+                    "Remaining".to_string(),
+                ],
+                vec![vec![
+                    year.to_string(),
+                    entitlement.to_string(),
+                    taken.to_string(),
+                    planned.to_string(),
+                    remaining.to_string(),
+                ]],
+                None,
+                &[serde_json::json!({
+                    "year": year,
+                    "entitlement": entitlement,
+                    "taken": taken,
+                    "planned": planned,
+                    "remaining": remaining,
+                })],
+            )?;
+        }
+        cli::Commands::Balance {
+            mut period,
+            per,
+            output: cli::OutputArgs { output },
+        } => {
+            if period.is_empty() {
+                period.period = Some("this month".to_string());
+            }
+            let config = config.read().await;
+            let today = config.today();
+            let (from, to) = period.resolve(&config)?;
+            let month_start = today.with_day(1).ok_or("Invalid date")?;
+            let month_end = month_start
+                .checked_add_months(Months::new(1))
+                .and_then(|date| date.pred_opt())
+                .ok_or("Invalid date")?;
+
+            // the balance carries over from the start of the first employment,
+            // the forecast needs the whole current month
+            let employments = moco_client.get_employments().await?;
+            let start = config
+                .balance_start()
+                .or_else(|| {
+                    employments
+                        .iter()
+                        .filter_map(|employment| {
+                            NaiveDate::parse_from_str(&employment.from, "%Y-%m-%d").ok()
+                        })
+                        .min()
+                })
+                .unwrap_or(from);
+            let fetch_from = from
+                .min(month_start)
+                .min(start)
+                .format("%Y-%m-%d")
+                .to_string();
+            let fetch_to = to.max(month_end).format("%Y-%m-%d").to_string();
+            let absences = moco_client
+                .get_schedules(fetch_from.clone(), fetch_to.clone(), None)
+                .await?;
+            let activities = moco_client
+                .get_activities(fetch_from, fetch_to, None, None)
+                .await?;
+
+            let carry_over: Duration = from
+                .pred_opt()
+                .filter(|before| start <= *before)
+                .map(|before| {
+                    balance::days(
+                        &employments,
+                        &absences,
+                        &activities,
+                        start,
+                        before.min(today),
+                    )
+                })
+                .unwrap_or_default()
+                .iter()
+                .map(|day| day.booked - day.target)
+                .sum();
+            let days = balance::days(
+                &employments,
+                &absences,
+                &activities,
+                from.max(start),
+                to.min(today),
+            );
+            let mut balance = carry_over;
+            let mut rows = vec![];
+            let mut records = vec![];
+            if start < from {
+                let key = format!("Before {}", from.format("%Y-%m-%d"));
+                rows.push(vec![
+                    key.clone(),
+                    "".to_string(),
+                    "".to_string(),
+                    "".to_string(),
+                    balance.format(duration_format),
+                ]);
+                records.push(serde_json::json!({
+                    "period": key,
+                    "balance_seconds": balance.seconds(),
+                }));
+            }
+            for (key, target, booked) in balance::group(&days, per, config.week_start()) {
+                balance = balance + booked - target;
+                rows.push(vec![
+                    key.clone(),
+                    target.format(duration_format),
+                    booked.format(duration_format),
+                    (booked - target).format(duration_format),
+                    balance.format(duration_format),
+                ]);
+                records.push(serde_json::json!({
+                    "period": key,
+                    "target_seconds": target.seconds(),
+                    "booked_seconds": booked.seconds(),
+                    "balance_seconds": balance.seconds(),
+                }));
+            }
+            let target: Duration = days.iter().map(|day| day.target).sum();
+            let booked: Duration = days.iter().map(|day| day.booked).sum();
+
+            render_output(
+                output,
+                vec![
+                    "Period".to_string(),
+                    "Target".to_string(),
+                    "Booked".to_string(),
+                    "Difference".to_string(),
+                    "Balance".to_string(),
+                ],
+                rows,
+                Some(vec![
+                    "-".to_string(),
+                    target.format(duration_format),
+                    booked.format(duration_format),
+                    (booked - target).format(duration_format),
+                    "".to_string(),
+                ]),
+                &records,
+            )?;
+
+            if from <= today && today <= to && utils::resolve_output(output) == cli::Output::Table {
+                let month =
+                    balance::days(&employments, &absences, &activities, month_start, month_end);
+                let target: Duration = month.iter().map(|day| day.target).sum();
+                let forecast = balance::forecast(&month, today);
+                println!(
+                    "Forecast for {}: {} of {} target hours, overtime {}",
+                    month_start.format("%Y-%m"),
+                    forecast.format(duration_format),
+                    target.format(duration_format),
+                    (forecast - target).format(duration_format)
+                );
+            }
+        }
+        cli::Commands::Check {
+            system: cli::Check::Worktime,
+            period,
+            output: cli::OutputArgs { output },
+        } => {
+            let config = config.read().await;
+            let (from, to) = period.resolve(&config)?;
+            let from = from.format("%Y-%m-%d").to_string();
+            let to = to.format("%Y-%m-%d").to_string();
+            let presences = moco_client.get_presences(from.clone(), to.clone()).await?;
+            let activities = moco_client.get_activities(from, to, None, None).await?;
+
+            let rules = worktime::Rules::new(&config);
+            let today = config.today().format("%Y-%m-%d").to_string();
+            let violations: Vec<worktime::Violation> =
+                worktime::days(&presences, &activities, &today, presence::now(&config))
+                    .iter()
+                    .flat_map(|day| worktime::check(&rules, day, duration_format))
+                    .collect();
+
+            if violations.is_empty() {
+                print_info(output, "No violations of the working time rules");
+                return Ok(());
+            }
+
+            render_output(
+                output,
+                vec![
+                    "Date".to_string(),
+                    "Rule".to_string(),
+                    "Actual".to_string(),
+                    "Limit".to_string(),
+                ],
+                violations
+                    .iter()
+                    .map(|violation| {
+                        vec![
+                            violation.date.clone(),
+                            violation.rule.clone(),
+                            violation.actual.format(duration_format),
+                            violation.limit.format(duration_format),
+                        ]
+                    })
+                    .collect(),
+                None,
+                &violations,
+            )?;
+            exit(1);
+        }
         cli::Commands::Sync {
             system,
-            today,
-            week,
-            last_week,
-            month,
-            last_month,
+            period,
             dry_run,
             project,
             task,
+            output: cli::OutputArgs { output },
         } => match system {
             cli::Sync::Jira => {
-                let (from, to) =
-                    utils::select_from_to_date(today, week, last_week, month, last_month);
+                let (from, to) = period.resolve(&*config.read().await)?;
 
                 let worklogs = tempo_client
                     .get_worklogs(
@@ -377,7 +1312,7 @@ async fn main() -> Result<(), Box<dyn Error>> {
 
                 trace!("Tempo: {:#?}", worklogs);
 
-                let (project, task) = prompt_task_select(&moco_client, project, task).await?;
+                let (project, task) = prompt_task_select(&moco_client, project, task, None).await?;
 
                 let activities = moco_client
                     .get_activities(
@@ -404,7 +1339,7 @@ async fn main() -> Result<(), Box<dyn Error>> {
                     .map(|worklog| CreateActivity {
                         remote_service: Some("jira".to_string()),
                         seconds: Some(worklog.time_spent_seconds),
-                        date: worklog.start_date.to_string(),
+                        date: worklog.start_date.clone(),
                         tag: Some("mococli".to_string()),
                         project_id: project.id,
                         task_id: task.id,
@@ -414,62 +1349,526 @@ async fn main() -> Result<(), Box<dyn Error>> {
                     })
                     .collect();
 
-                let output_list = vec![
-                    "Date",
-                    "Duration (hours)",
-                    "Description",
-                    "Project ID",
-                    "Task ID",
-                ];
+                sync_activities(
+                    &moco_client,
+                    from,
+                    to,
+                    worklogs,
+                    dry_run,
+                    output,
+                    &*config.read().await,
+                )
+                .await?;
+            }
+        },
+        cli::Commands::Import {
+            system,
+            files,
+            period,
+            project,
+            task,
+            dry_run,
+            output: cli::OutputArgs { output },
+        } => match system {
+            cli::Import::Org => {
+                let (from, to) = period.resolve(&*config.read().await)?;
+
+                let mut entries = vec![];
+                for file in &files {
+                    entries.append(&mut import::org::parse(&std::fs::read_to_string(file)?));
+                }
+                entries.retain(|entry| entry.date >= from && entry.date <= to);
+
+                trace!("Org: {:#?}", entries);
+
+                let tag_tasks = config
+                    .read()
+                    .await
+                    .org_tag_tasks
+                    .clone()
+                    .unwrap_or_default();
+                let mut task_mapper = TaskMapper::new(&moco_client, project, task).await?;
+
+                let mut activities = vec![];
+                for entry in entries {
+                    let task_id = entry.moco_task.or_else(|| {
+                        entry.tags.iter().find_map(|tag| {
+                            tag_tasks
+                                .iter()
+                                .find(|(mapped_tag, _)| mapped_tag.eq_ignore_ascii_case(tag))
+                                .map(|(_, task_id)| *task_id)
+                        })
+                    });
+                    let (project, task) = task_mapper.resolve(task_id, &entry.heading).await?;
+
+                    activities.push(CreateActivity {
+                        remote_service: Some("org".to_string()),
+                        seconds: Some(entry.seconds),
+                        date: entry.date.format("%Y-%m-%d").to_string(),
+                        tag: Some("mococli".to_string()),
+                        project_id: project.id,
+                        task_id: task.id,
+                        description: entry.heading,
+                        remote_id: Some(entry.remote_id),
+                        ..Default::default()
+                    });
+                }
+
+                if !utils::update_synced_activities(
+                    &moco_client,
+                    &activities,
+                    dry_run,
+                    args.yes,
+                    output,
+                    &*config.read().await,
+                )
+                .await?
+                {
+                    println!("Aborted");
+                    return Ok(());
+                }
+                sync_activities(
+                    &moco_client,
+                    from,
+                    to,
+                    activities,
+                    dry_run,
+                    output,
+                    &*config.read().await,
+                )
+                .await?;
+            }
+            cli::Import::Ics => {
+                let (from, to) = period.resolve(&*config.read().await)?;
+
+                let (email, rules, timezone) = {
+                    let config = config.read().await;
+                    (
+                        config.ics_email.clone(),
+                        config.ics_rules.clone().unwrap_or_default(),
+                        config.timezone(),
+                    )
+                };
+
+                let mut events = vec![];
+                for file in &files {
+                    events.append(&mut import::ics::parse(
+                        &std::fs::read_to_string(file)?,
+                        from,
+                        to,
+                        email.as_deref(),
+                        &timezone,
+                    ));
+                }
+
+                trace!("ICS: {:#?}", events);
 
-                let mut output_list = vec![output_list.iter().map(|str| str.to_string()).collect()];
+                let mut task_mapper = TaskMapper::new(&moco_client, project, task).await?;
 
-                for worklog in &worklogs {
-                    output_list.push(vec![
-                        worklog.date.clone(),
-                        worklog
-                            .seconds
-                            .map(|seconds| seconds as f64 / 60.0 / 60.0)
-                            .unwrap_or(0.0)
-                            .to_string(),
-                        worklog.description.clone(),
-                        worklog.project_id.to_string(),
-                        worklog.task_id.to_string(),
-                    ])
+                let mut activities = vec![];
+                for event in events {
+                    let task_id = import::ics::find_task(&rules, &event);
+                    let (project, task) = task_mapper.resolve(task_id, &event.summary).await?;
+
+                    activities.push(CreateActivity {
+                        remote_service: Some("ics".to_string()),
+                        seconds: Some(event.seconds),
+                        date: event.date.format("%Y-%m-%d").to_string(),
+                        tag: Some("mococli".to_string()),
+                        project_id: project.id,
+                        task_id: task.id,
+                        description: event.summary,
+                        remote_id: Some(event.remote_id),
+                        ..Default::default()
+                    });
                 }
 
-                if dry_run {
-                    println!("Planned sync: ");
-                    println!(
-                        "From {} to {}",
-                        from.format("%d.%m.%y"),
-                        to.format("%d.%m.%y")
-                    );
-                    if output_list.len() == 1 {
-                        print!("Nothing, everything seems to be synced!")
-                    } else {
-                        render_table(output_list);
+                sync_activities(
+                    &moco_client,
+                    from,
+                    to,
+                    activities,
+                    dry_run,
+                    output,
+                    &*config.read().await,
+                )
+                .await?;
+            }
+            cli::Import::Toggl => {
+                let (from, to) = period.resolve(&*config.read().await)?;
+
+                let mut contents = vec![];
+                for file in &files {
+                    contents.push(std::fs::read_to_string(file)?);
+                }
+                let mut entries = import::toggl::parse(&contents)?;
+                entries.retain(|entry| entry.date >= from && entry.date <= to);
+
+                trace!("Toggl: {:#?}", entries);
+
+                let rules = config.read().await.toggl_rules.clone().unwrap_or_default();
+                let mut task_mapper = TaskMapper::new(&moco_client, project, task).await?;
+
+                let mut activities = vec![];
+                for entry in entries {
+                    let task_id = import::toggl::find_task(&rules, &entry);
+                    let (project, task) = task_mapper
+                        .resolve(task_id, &format!("{} / {}", entry.client, entry.project))
+                        .await?;
+
+                    activities.push(CreateActivity {
+                        remote_service: Some("toggl".to_string()),
+                        seconds: Some(entry.seconds),
+                        date: entry.date.format("%Y-%m-%d").to_string(),
+                        tag: Some("mococli".to_string()),
+                        project_id: project.id,
+                        task_id: task.id,
+                        description: entry.description,
+                        remote_id: Some(entry.remote_id),
+                        ..Default::default()
+                    });
+                }
+
+                sync_activities(
+                    &moco_client,
+                    from,
+                    to,
+                    activities,
+                    dry_run,
+                    output,
+                    &*config.read().await,
+                )
+                .await?;
+            }
+            cli::Import::Activitywatch => {
+                let (from, to) = period.resolve(&*config.read().await)?;
+
+                let (rules, timezone) = {
+                    let config = config.read().await;
+                    (
+                        config.activitywatch_rules.clone().unwrap_or_default(),
+                        config.timezone(),
+                    )
+                };
+
+                let mut suggestions = vec![];
+                for file in &files {
+                    suggestions.append(&mut import::activitywatch::parse(
+                        &std::fs::read_to_string(file)?,
+                        &rules,
+                        &timezone,
+                    )?);
+                }
+                suggestions.retain(|suggestion| suggestion.date >= from && suggestion.date <= to);
+
+                trace!("ActivityWatch: {:#?}", suggestions);
+
+                let mut task_mapper = TaskMapper::new(&moco_client, project, task).await?;
+
+                let mut activities = vec![];
+                for suggestion in suggestions {
+                    let (project, task) = task_mapper
+                        .resolve(suggestion.task, &suggestion.description)
+                        .await?;
+
+                    activities.push((
+                        CreateActivity {
+                            remote_service: Some("activitywatch".to_string()),
+                            seconds: Some(suggestion.seconds),
+                            date: suggestion.date.format("%Y-%m-%d").to_string(),
+                            tag: Some("mococli".to_string()),
+                            project_id: project.id,
+                            task_id: task.id,
+                            description: suggestion.description,
+                            remote_id: Some(suggestion.remote_id),
+                            ..Default::default()
+                        },
+                        project,
+                        task,
+                    ));
+                }
+
+                let unsynced = utils::remove_synced_activities(
+                    &moco_client,
+                    from,
+                    to,
+                    activities
+                        .iter()
+                        .map(|(activity, _, _)| activity.clone())
+                        .collect(),
+                )
+                .await?;
+
+                let mut accepted = vec![];
+                for (activity, project, task) in activities {
+                    if !unsynced.contains(&activity) {
+                        continue;
                     }
-                    println!();
-                } else {
-                    println!("Sync plan: ");
-                    println!(
-                        "From {} to {}",
-                        from.format("%d.%m.%y"),
-                        to.format("%d.%m.%y")
-                    );
-                    if output_list.len() == 1 {
-                        print!("Nothing, everything seems to be synced!")
+                    if args.yes {
+                        accepted.push(activity);
+                        continue;
+                    }
+                    if let Some(activity) = utils::prompt_activity_review(
+                        &moco_client,
+                        activity,
+                        &project,
+                        &task,
+                        duration_format,
+                    )
+                    .await?
+                    {
+                        accepted.push(activity);
+                    }
+                }
+                print_info(output, "");
+
+                sync_activities(
+                    &moco_client,
+                    from,
+                    to,
+                    accepted,
+                    dry_run,
+                    output,
+                    &*config.read().await,
+                )
+                .await?;
+            }
+            cli::Import::Csv => {
+                let mapping = config.read().await.csv_mapping.clone().unwrap_or_default();
+                let projects = moco_client.get_assigned_projects().await?;
+
+                let mut rows = vec![];
+                let mut errors = vec![];
+                for file in &files {
+                    match import::csv::parse(
+                        &std::fs::read_to_string(file)?,
+                        &mapping,
+                        &projects,
+                        project,
+                        task,
+                    ) {
+                        Ok(mut file_rows) => rows.append(&mut file_rows),
+                        Err(file_errors) => errors.extend(
+                            file_errors
+                                .iter()
+                                .map(|e| format!("{}: {}", file.display(), e)),
+                        ),
+                    }
+                }
+
+                if !errors.is_empty() {
+                    for error in errors {
+                        println!("{}", error);
+                    }
+                    println!("Nothing was imported");
+                    exit(1);
+                }
+
+                trace!("CSV: {:#?}", rows);
+
+                // all rows are imported unless a period is given
+                if !period.is_empty() {
+                    let (from, to) = period.resolve(&*config.read().await)?;
+                    rows.retain(|row| from <= row.date && row.date <= to);
+                }
+
+                if rows.is_empty() {
+                    print_info(output, "Nothing to import");
+                    return Ok(());
+                }
+
+                let from = rows.iter().map(|row| row.date).min().unwrap();
+                let to = rows.iter().map(|row| row.date).max().unwrap();
+
+                let (updates, creates): (Vec<_>, Vec<_>) =
+                    rows.into_iter().partition(|row| row.id.is_some());
+
+                let existing_activities = moco_client
+                    .get_activities(
+                        from.format("%Y-%m-%d").to_string(),
+                        to.format("%Y-%m-%d").to_string(),
+                        None,
+                        None,
+                    )
+                    .await?;
+
+                let mut edits = vec![];
+                let mut durations = vec![];
+                for row in updates {
+                    let activity_id = row.id.unwrap();
+                    let activity = match existing_activities.iter().find(|a| a.id == activity_id) {
+                        Some(activity) => activity.clone(),
+                        None => moco_client
+                            .get_activity(&GetActivity { activity_id })
+                            .await
+                            .map_err(|_| format!("Activity {} does not exist", activity_id))?,
+                    };
+
+                    let date = row.date.format("%Y-%m-%d").to_string();
+                    // Unchanged durations are kept as booked, even if they are not rounded
+                    let rounded = if activity.duration() == row.duration {
+                        row.duration
                     } else {
-                        render_table(output_list);
+                        config.read().await.round(row.duration)
+                    };
+                    if activity.date == date
+                        && activity.duration() == rounded
+                        && activity.project.id == row.project.id
+                        && activity.task.id == row.task.id
+                        && activity.description.as_deref().unwrap_or_default() == row.description
+                        && row
+                            .billable
+                            .is_none_or(|billable| billable == activity.billable)
+                        && row.tag.as_ref().is_none_or(|tag| *tag == activity.tag)
+                        && row
+                            .remote_service
+                            .as_ref()
+                            .is_none_or(|service| activity.remote_service.as_ref() == Some(service))
+                        && row
+                            .remote_id
+                            .as_ref()
+                            .is_none_or(|id| activity.remote_id.as_ref() == Some(id))
+                        && row
+                            .remote_url
+                            .as_ref()
+                            .is_none_or(|url| activity.remote_url.as_str() == Some(url))
+                    {
+                        continue;
+                    }
+
+                    edits.push(EditActivity {
+                        activity_id,
+                        project_id: row.project.id,
+                        task_id: row.task.id,
+                        date,
+                        description: row.description,
+                        seconds: rounded.seconds(),
+                        billable: row.billable,
+                        tag: row.tag,
+                        remote_service: row.remote_service,
+                        remote_id: row.remote_id,
+                        remote_url: row.remote_url,
+                    });
+                    durations.push(row.duration);
+                }
+
+                if !edits.is_empty() {
+                    print_info(output, "Updates: ");
+                    render_output(
+                        output,
+                        [
+                            "Activity ID",
+                            "Date",
+                            "Duration",
+                            "Rounded",
+                            "Description",
+                            "Project ID",
+                            "Task ID",
+                        ]
+                        .iter()
+                        .map(|str| str.to_string())
+                        .collect(),
+                        edits
+                            .iter()
+                            .zip(durations)
+                            .map(|(edit, duration)| {
+                                vec![
+                                    edit.activity_id.to_string(),
+                                    edit.date.clone(),
+                                    duration.format(duration_format),
+                                    Duration::from_seconds(edit.seconds).format(duration_format),
+                                    edit.description.clone(),
+                                    edit.project_id.to_string(),
+                                    edit.task_id.to_string(),
+                                ]
+                            })
+                            .collect(),
+                        None,
+                        &edits,
+                    )?;
+                    print_info(output, "");
+
+                    let question = format!("Overwrite {} existing activities?", edits.len());
+                    if !dry_run && !utils::confirm(&question, args.yes)? {
+                        println!("Aborted");
+                        return Ok(());
                     }
+                }
 
-                    println!();
+                let creates = creates
+                    .into_iter()
+                    .map(|row| CreateActivity {
+                        date: row.date.format("%Y-%m-%d").to_string(),
+                        description: row.description,
+                        project_id: row.project.id,
+                        task_id: row.task.id,
+                        seconds: Some(row.duration.seconds()),
+                        billable: row.billable,
+                        tag: row.tag,
+                        remote_service: row.remote_service,
+                        remote_id: row.remote_id,
+                        remote_url: row.remote_url,
+                        ..Default::default()
+                    })
+                    .collect();
+
+                sync_activities(
+                    &moco_client,
+                    from,
+                    to,
+                    creates,
+                    dry_run,
+                    output,
+                    &*config.read().await,
+                )
+                .await?;
+
+                if !dry_run {
+                    let mut failed = 0;
+                    for edit in &edits {
+                        if let Err(e) = moco_client.edit_activity(edit).await {
+                            eprintln!("Could not update activity {}: {}", edit.activity_id, e);
+                            failed += 1;
+                        }
+                    }
+                    if failed > 0 {
+                        println!("{} of {} updates failed", failed, edits.len());
+                        exit(1);
+                    }
+                }
+            }
+        },
+        cli::Commands::Export {
+            system,
+            file,
+            period,
+        } => match system {
+            cli::Export::Csv => {
+                let (from, to) = period.resolve(&*config.read().await)?;
 
-                    for worklog in worklogs {
-                        moco_client.create_activity(&worklog).await?;
+                let mut activities = moco_client
+                    .get_activities(
+                        from.format("%Y-%m-%d").to_string(),
+                        to.format("%Y-%m-%d").to_string(),
+                        None,
+                        None,
+                    )
+                    .await?;
+                activities.sort_by(|a, b| a.date.cmp(&b.date));
+
+                let delimiter = config
+                    .read()
+                    .await
+                    .csv_mapping
+                    .clone()
+                    .unwrap_or_default()
+                    .delimiter;
+
+                match file {
+                    Some(file) => {
+                        export::write_csv(&activities, delimiter, std::fs::File::create(file)?)?
                     }
-                    println!("Synced!");
+                    None => export::write_csv(&activities, delimiter, std::io::stdout())?,
                 }
             }
         },
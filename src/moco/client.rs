@@ -1,11 +1,12 @@
 use std::{error::Error, sync::Arc};
 
-use reqwest::Client;
+use reqwest::{Client, RequestBuilder};
+use serde::de::DeserializeOwned;
 use tokio::sync::RwLock;
 
 use crate::moco::model::{
-    Activity, ControlActivityTimer, CreateActivity, DeleteActivity, EditActivity, Employment,
-    GetActivity, Projects,
+    Activity, ActivityQuery, ControlActivityTimer, CreateActivity, CreateSchedule, DeleteActivity,
+    EditActivity, Employment, GetActivity, Presence, Projects, Schedule, UserHoliday,
 };
 
 use crate::config::AppConfig;
@@ -36,6 +37,35 @@ impl MocoClient {
         })
     }
 
+    /// Reads all pages of a list, Moco returns the number of entries in `X-Total`
+    async fn get_all_pages<T: DeserializeOwned>(
+        &self,
+        request: RequestBuilder,
+    ) -> Result<Vec<T>, Box<dyn Error>> {
+        let mut entries = vec![];
+        for page in 1.. {
+            let response = request
+                .try_clone()
+                .ok_or("Request can not be repeated")?
+                .query(&[("page", page)])
+                .send()
+                .await?;
+            let total = response
+                .headers()
+                .get("x-total")
+                .and_then(|total| total.to_str().ok())
+                .and_then(|total| total.parse::<usize>().ok());
+            let mut batch = response.json::<Vec<T>>().await?;
+            let done =
+                batch.is_empty() || total.is_none_or(|total| entries.len() + batch.len() >= total);
+            entries.append(&mut batch);
+            if done {
+                break;
+            }
+        }
+        Ok(entries)
+    }
+
     pub async fn get_user_id(
         &self,
         firstname: String,
@@ -66,6 +96,30 @@ impl MocoClient {
         }
     }
 
+    /// Employment history of the user
+    pub async fn get_employments(&self) -> Result<Vec<Employment>, Box<dyn Error>> {
+        let parameter = vec![(
+            "user_id",
+            format!("{}", &self.config.read().await.moco_user_id.unwrap()),
+        )];
+
+        let config = &self.config.read().await;
+        match (config.moco_api_key.as_ref(), config.moco_company.as_ref()) {
+            (Some(api_key), Some(company)) => Ok(self
+                .client
+                .get(format!(
+                    "https://{company}.mocoapp.com/api/v1/users/employments"
+                ))
+                .query(&parameter)
+                .header("Authorization", format!("Token token={}", api_key))
+                .send()
+                .await?
+                .json::<Vec<Employment>>()
+                .await?),
+            (_, _) => Err(Box::new(MocoClientError::NotLoggedIn)),
+        }
+    }
+
     pub async fn get_activities(
         &self,
         from: String,
@@ -73,7 +127,25 @@ impl MocoClient {
         task_id: Option<String>,
         term: Option<String>,
     ) -> Result<Vec<Activity>, Box<dyn Error>> {
-        let mut parameter = vec![
+        self.query_activities(
+            from,
+            to,
+            &ActivityQuery {
+                task_id: task_id.and_then(|x| x.parse::<i64>().ok()),
+                term,
+                ..Default::default()
+            },
+        )
+        .await
+    }
+
+    pub async fn query_activities(
+        &self,
+        from: String,
+        to: String,
+        query: &ActivityQuery,
+    ) -> Result<Vec<Activity>, Box<dyn Error>> {
+        let parameter = vec![
             ("from", from),
             ("to", to),
             (
@@ -82,24 +154,18 @@ impl MocoClient {
             ),
         ];
 
-        if let Some(x) = task_id {
-            parameter.push(("task_id", x))
-        }
-        if let Some(x) = term {
-            parameter.push(("term", x))
-        }
-
         let config = &self.config.read().await;
         match (config.moco_api_key.as_ref(), config.moco_company.as_ref()) {
-            (Some(api_key), Some(company)) => Ok(self
-                .client
-                .get(format!("https://{company}.mocoapp.com/api/v1/activities"))
-                .query(&parameter)
-                .header("Authorization", format!("Token token={}", api_key))
-                .send()
-                .await?
-                .json::<Vec<Activity>>()
-                .await?),
+            (Some(api_key), Some(company)) => {
+                self.get_all_pages(
+                    self.client
+                        .get(format!("https://{company}.mocoapp.com/api/v1/activities"))
+                        .query(&parameter)
+                        .query(query)
+                        .header("Authorization", format!("Token token={}", api_key)),
+                )
+                .await
+            }
             (_, _) => Err(Box::new(MocoClientError::NotLoggedIn)),
         }
     }
@@ -122,18 +188,22 @@ impl MocoClient {
         }
     }
 
-    pub async fn create_activity(&self, payload: &CreateActivity) -> Result<(), Box<dyn Error>> {
+    pub async fn create_activity(
+        &self,
+        payload: &CreateActivity,
+    ) -> Result<Activity, Box<dyn Error>> {
         let config = &self.config.read().await;
         match (config.moco_api_key.as_ref(), config.moco_company.as_ref()) {
-            (Some(api_key), Some(company)) => {
-                self.client
-                    .post(format!("https://{company}.mocoapp.com/api/v1/activities"))
-                    .header("Authorization", format!("Token token={}", api_key))
-                    .json(payload)
-                    .send()
-                    .await?;
-                Ok(())
-            }
+            (Some(api_key), Some(company)) => Ok(self
+                .client
+                .post(format!("https://{company}.mocoapp.com/api/v1/activities"))
+                .header("Authorization", format!("Token token={}", api_key))
+                .json(payload)
+                .send()
+                .await?
+                .error_for_status()?
+                .json::<Activity>()
+                .await?),
             (_, _) => Err(Box::new(MocoClientError::NotLoggedIn)),
         }
     }
@@ -150,7 +220,8 @@ impl MocoClient {
                     .header("Authorization", format!("Token token={}", api_key))
                     .json(payload)
                     .send()
-                    .await?;
+                    .await?
+                    .error_for_status()?;
                 Ok(())
             }
             (_, _) => Err(Box::new(MocoClientError::NotLoggedIn)),
@@ -212,4 +283,124 @@ impl MocoClient {
             (_, _) => Err(Box::new(MocoClientError::NotLoggedIn)),
         }
     }
+
+    pub async fn get_presences(
+        &self,
+        from: String,
+        to: String,
+    ) -> Result<Vec<Presence>, Box<dyn Error>> {
+        let config = &self.config.read().await;
+        match (config.moco_api_key.as_ref(), config.moco_company.as_ref()) {
+            (Some(api_key), Some(company)) => {
+                self.get_all_pages(
+                    self.client
+                        .get(format!(
+                            "https://{company}.mocoapp.com/api/v1/users/presences"
+                        ))
+                        .query(&[("from", from), ("to", to)])
+                        .header("Authorization", format!("Token token={}", api_key)),
+                )
+                .await
+            }
+            (_, _) => Err(Box::new(MocoClientError::NotLoggedIn)),
+        }
+    }
+
+    pub async fn get_schedules(
+        &self,
+        from: String,
+        to: String,
+        absence_code: Option<i64>,
+    ) -> Result<Vec<Schedule>, Box<dyn Error>> {
+        let mut parameter = vec![
+            ("from", from),
+            ("to", to),
+            (
+                "user_id",
+                format!("{}", &self.config.read().await.moco_user_id.unwrap()),
+            ),
+        ];
+        if let Some(absence_code) = absence_code {
+            parameter.push(("absence_code", absence_code.to_string()));
+        }
+
+        let config = &self.config.read().await;
+        match (config.moco_api_key.as_ref(), config.moco_company.as_ref()) {
+            (Some(api_key), Some(company)) => {
+                self.get_all_pages(
+                    self.client
+                        .get(format!("https://{company}.mocoapp.com/api/v1/schedules"))
+                        .query(&parameter)
+                        .header("Authorization", format!("Token token={}", api_key)),
+                )
+                .await
+            }
+            (_, _) => Err(Box::new(MocoClientError::NotLoggedIn)),
+        }
+    }
+
+    pub async fn create_schedule(
+        &self,
+        payload: &CreateSchedule,
+    ) -> Result<Schedule, Box<dyn Error>> {
+        let config = &self.config.read().await;
+        match (config.moco_api_key.as_ref(), config.moco_company.as_ref()) {
+            (Some(api_key), Some(company)) => Ok(self
+                .client
+                .post(format!("https://{company}.mocoapp.com/api/v1/schedules"))
+                .header("Authorization", format!("Token token={}", api_key))
+                .json(payload)
+                .send()
+                .await?
+                .error_for_status()?
+                .json::<Schedule>()
+                .await?),
+            (_, _) => Err(Box::new(MocoClientError::NotLoggedIn)),
+        }
+    }
+
+    /// Vacation entitlements of the year
+    pub async fn get_user_holidays(&self, year: i32) -> Result<Vec<UserHoliday>, Box<dyn Error>> {
+        let parameter = vec![
+            ("year", year.to_string()),
+            (
+                "user_id",
+                format!("{}", &self.config.read().await.moco_user_id.unwrap()),
+            ),
+        ];
+
+        let config = &self.config.read().await;
+        match (config.moco_api_key.as_ref(), config.moco_company.as_ref()) {
+            (Some(api_key), Some(company)) => Ok(self
+                .client
+                .get(format!(
+                    "https://{company}.mocoapp.com/api/v1/users/holidays"
+                ))
+                .query(&parameter)
+                .header("Authorization", format!("Token token={}", api_key))
+                .send()
+                .await?
+                .json::<Vec<UserHoliday>>()
+                .await?),
+            (_, _) => Err(Box::new(MocoClientError::NotLoggedIn)),
+        }
+    }
+
+    /// Clocks in, or out if there is an open presence today
+    pub async fn touch_presence(&self) -> Result<Presence, Box<dyn Error>> {
+        let config = &self.config.read().await;
+        match (config.moco_api_key.as_ref(), config.moco_company.as_ref()) {
+            (Some(api_key), Some(company)) => Ok(self
+                .client
+                .post(format!(
+                    "https://{company}.mocoapp.com/api/v1/users/presences/touch"
+                ))
+                .header("Authorization", format!("Token token={}", api_key))
+                .send()
+                .await?
+                .json::<Presence>()
+                .await?),
+            (_, _) => Err(Box::new(MocoClientError::NotLoggedIn)),
+        }
+    }
 }
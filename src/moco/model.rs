@@ -2,6 +2,10 @@ use serde::Deserialize;
 use serde::Serialize;
 use serde_json::Value;
 
+use chrono::{Datelike, NaiveDate, NaiveTime};
+
+use crate::duration::{parse_time, Duration};
+
 // Employment
 
 #[derive(Default, Debug, Clone, PartialEq, Serialize, Deserialize)]
@@ -20,6 +24,21 @@ pub struct Employment {
     pub updated_at: String,
 }
 
+impl Employment {
+    /// The employment is valid on the date, current employments have no end
+    pub fn covers(&self, date: &str) -> bool {
+        self.from.as_str() <= date && self.to.as_str().is_none_or(|to| date <= to)
+    }
+
+    /// Target hours of the morning and afternoon of the date
+    pub fn target(&self, date: NaiveDate) -> (Duration, Duration) {
+        let weekday = date.weekday().num_days_from_monday() as usize;
+        let hours =
+            |pattern: &[f64]| Duration::from_hours(pattern.get(weekday).copied().unwrap_or(0.0));
+        (hours(&self.pattern.am), hours(&self.pattern.pm))
+    }
+}
+
 #[derive(Default, Debug, Clone, PartialEq, Serialize, Deserialize)]
 #[serde(rename_all = "camelCase")]
 pub struct Pattern {
@@ -68,6 +87,12 @@ pub struct Activity {
     pub hourly_rate: f64,
 }
 
+impl Activity {
+    pub fn duration(&self) -> Duration {
+        Duration::from_seconds(self.seconds)
+    }
+}
+
 #[derive(Default, Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
 #[serde(rename_all = "camelCase")]
 pub struct ActivityProject {
@@ -91,6 +116,19 @@ pub struct Customer {
     pub name: String,
 }
 
+#[derive(Default, Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ActivityQuery {
+    #[serde(rename = "project_id", skip_serializing_if = "Option::is_none")]
+    pub project_id: Option<i64>,
+    #[serde(rename = "task_id", skip_serializing_if = "Option::is_none")]
+    pub task_id: Option<i64>,
+    #[serde(rename = "company_id", skip_serializing_if = "Option::is_none")]
+    pub company_id: Option<i64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub term: Option<String>,
+}
+
 #[derive(Default, Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
 #[serde(rename_all = "camelCase")]
 pub struct GetActivity {
@@ -109,6 +147,8 @@ pub struct CreateActivity {
     pub task_id: i64,
     pub hours: Option<f64>,
     pub seconds: Option<i64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub billable: Option<bool>,
     pub tag: Option<String>,
     #[serde(rename = "remote_service")]
     pub remote_service: Option<String>,
@@ -129,7 +169,17 @@ pub struct EditActivity {
     pub task_id: i64,
     pub date: String,
     pub description: String,
-    pub hours: String,
+    pub seconds: i64,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub billable: Option<bool>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub tag: Option<String>,
+    #[serde(rename = "remote_service", skip_serializing_if = "Option::is_none")]
+    pub remote_service: Option<String>,
+    #[serde(rename = "remote_id", skip_serializing_if = "Option::is_none")]
+    pub remote_id: Option<String>,
+    #[serde(rename = "remote_url", skip_serializing_if = "Option::is_none")]
+    pub remote_url: Option<String>,
 }
 
 #[derive(Default, Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
@@ -147,6 +197,104 @@ pub struct DeleteActivity {
     pub activity_id: i64,
 }
 
+// Presence
+
+#[derive(Default, Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Presence {
+    pub id: i64,
+    pub date: String,
+    pub from: String,
+    pub to: Option<String>,
+    #[serde(rename = "is_home_office")]
+    pub is_home_office: bool,
+}
+
+impl Presence {
+    pub fn is_open(&self) -> bool {
+        self.to.as_deref().is_none_or(str::is_empty)
+    }
+
+    /// Open presences last until `now`, or are empty if it is not given
+    pub fn duration(&self, now: Option<NaiveTime>) -> Duration {
+        let from = parse_time(&self.from).ok();
+        let to = match self.to.as_deref() {
+            Some(to) if !to.is_empty() => parse_time(to).ok(),
+            _ => now,
+        };
+        match (from, to) {
+            (Some(from), Some(to)) => Duration::between(from, to).unwrap_or_default(),
+            _ => Duration::default(),
+        }
+    }
+}
+
+// Schedule
+
+/// Absence codes of schedules
+pub const ABSENCE_UNPLANNABLE: i64 = 1;
+pub const ABSENCE_SICK_DAY: i64 = 3;
+pub const ABSENCE_VACATION: i64 = 4;
+pub const ABSENCE_ABSENCE: i64 = 5;
+
+#[derive(Default, Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Schedule {
+    pub id: i64,
+    pub date: String,
+    pub comment: Option<String>,
+    pub am: bool,
+    pub pm: bool,
+    pub assignment: ScheduleAssignment,
+}
+
+impl Schedule {
+    /// Absence code of absences, the id of their assignment
+    pub fn absence_code(&self) -> Option<i64> {
+        (self.assignment.kind == "Absence").then_some(self.assignment.id)
+    }
+
+    /// Half days count as 0.5
+    pub fn days(&self) -> f64 {
+        match (self.am, self.pm) {
+            (true, true) | (false, false) => 1.0,
+            _ => 0.5,
+        }
+    }
+}
+
+#[derive(Default, Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ScheduleAssignment {
+    pub id: i64,
+    pub name: String,
+    #[serde(rename = "type")]
+    pub kind: String,
+}
+
+#[derive(Default, Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CreateSchedule {
+    pub date: String,
+    #[serde(rename = "absence_code")]
+    pub absence_code: i64,
+    pub am: bool,
+    pub pm: bool,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub comment: Option<String>,
+}
+
+/// Vacation entitlement of a year
+#[derive(Default, Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct UserHoliday {
+    pub id: i64,
+    pub year: i32,
+    pub title: String,
+    pub days: f64,
+    pub hours: f64,
+}
+
 //Project
 
 pub type Projects = Vec<Project>;
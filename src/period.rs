@@ -0,0 +1,189 @@
+use std::error::Error;
+
+use chrono::{Datelike, Duration, Months, NaiveDate, Weekday};
+
+use crate::cli::PeriodArgs;
+use crate::config::AppConfig;
+
+impl PeriodArgs {
+    /// Resolves the period options to the first and last day, defaults to today
+    pub fn resolve(&self, config: &AppConfig) -> Result<(NaiveDate, NaiveDate), Box<dyn Error>> {
+        let today = config.today();
+        let week_start = config.week_start();
+        let parse = |expression: &str| parse_period(expression, today, week_start);
+
+        let expression = if let Some(expression) = &self.period {
+            expression.as_str()
+        } else if self.week {
+            "this week"
+        } else if self.last_week {
+            "last week"
+        } else if self.month {
+            "this month"
+        } else if self.last_month {
+            "last month"
+        } else {
+            "today"
+        };
+        let (mut from, mut to) = parse(expression)?;
+
+        if let Some(expression) = &self.from {
+            from = parse(expression)?.0;
+            if self.to.is_none() && self.period.is_none() {
+                to = today;
+            }
+        }
+        if let Some(expression) = &self.to {
+            to = parse(expression)?.1;
+        }
+
+        if from > to {
+            return Err(format!("Period starts at {} after its end {}", from, to).into());
+        }
+
+        Ok((from, to))
+    }
+
+    /// No period option is given
+    pub fn is_empty(&self) -> bool {
+        !(self.today || self.week || self.last_week || self.month || self.last_month)
+            && self.period.is_none()
+            && self.from.is_none()
+            && self.to.is_none()
+    }
+}
+
+/// Parses period expressions like `yesterday`, `last week`, `2024-W18`,
+/// `2024-05`, `Q2`, `2024`, `last 10 days` or `since monday`
+pub fn parse_period(
+    expression: &str,
+    today: NaiveDate,
+    week_start: Weekday,
+) -> Result<(NaiveDate, NaiveDate), String> {
+    let expression = expression.trim().to_lowercase();
+    let words: Vec<&str> = expression.split_whitespace().collect();
+
+    let period = match words.as_slice() {
+        ["today"] => Some((today, today)),
+        ["yesterday"] => Some((today - Duration::days(1), today - Duration::days(1))),
+        ["tomorrow"] => Some((today + Duration::days(1), today + Duration::days(1))),
+        ["week"] | ["this", "week"] => Some(week_of(today, week_start)),
+        ["last", "week"] => Some(week_of(today - Duration::weeks(1), week_start)),
+        ["next", "week"] => Some(week_of(today + Duration::weeks(1), week_start)),
+        ["month"] | ["this", "month"] => month_of(today),
+        ["last", "month"] => today.checked_sub_months(Months::new(1)).and_then(month_of),
+        ["quarter"] | ["this", "quarter"] => quarter_of(today.year(), quarter(today)),
+        ["last", "quarter"] => today
+            .checked_sub_months(Months::new(3))
+            .and_then(|date| quarter_of(date.year(), quarter(date))),
+        ["year"] | ["this", "year"] => year_of(today.year()),
+        ["last", "year"] => year_of(today.year() - 1),
+        ["last", count, unit] => count
+            .parse::<u32>()
+            .ok()
+            .filter(|count| *count > 0)
+            .and_then(|count| last(today, count, unit)),
+        ["since", start] => parse_single(start, today).map(|(from, _)| (from, today)),
+        [single] => parse_single(single, today),
+        _ => None,
+    };
+
+    period.ok_or(format!(
+        "Unknown period \"{}\", try e.g. yesterday, last week, 2024-W18, 2024-05, Q2, 2024, last 10 days or since monday",
+        expression
+    ))
+}
+
+fn parse_single(expression: &str, today: NaiveDate) -> Option<(NaiveDate, NaiveDate)> {
+    if let Ok(date) = NaiveDate::parse_from_str(expression, "%Y-%m-%d") {
+        return Some((date, date));
+    }
+
+    // Weekdays refer to the last occurrence, today included
+    if let Ok(weekday) = expression.parse::<Weekday>() {
+        let days_back =
+            (7 + today.weekday().num_days_from_monday() - weekday.num_days_from_monday()) % 7;
+        let date = today - Duration::days(days_back as i64);
+        return Some((date, date));
+    }
+
+    if let Some(quarter) = expression.strip_prefix('q') {
+        return quarter
+            .parse::<u32>()
+            .ok()
+            .and_then(|quarter| quarter_of(today.year(), quarter));
+    }
+
+    if let Ok(year) = expression.parse::<i32>() {
+        return year_of(year);
+    }
+
+    let (year, rest) = expression.split_once('-')?;
+    let year = year.parse::<i32>().ok()?;
+
+    if let Some(week) = rest.strip_prefix('w') {
+        let monday = NaiveDate::from_isoywd_opt(year, week.parse::<u32>().ok()?, Weekday::Mon)?;
+        return Some((monday, monday + Duration::days(6)));
+    }
+
+    if let Some(quarter) = rest.strip_prefix('q') {
+        return quarter_of(year, quarter.parse::<u32>().ok()?);
+    }
+
+    month_of(NaiveDate::from_ymd_opt(year, rest.parse::<u32>().ok()?, 1)?)
+}
+
+fn last(today: NaiveDate, count: u32, unit: &str) -> Option<(NaiveDate, NaiveDate)> {
+    let from = match unit {
+        "day" | "days" => today - Duration::days(count as i64 - 1),
+        "week" | "weeks" => today - Duration::days(count as i64 * 7 - 1),
+        "month" | "months" => today.checked_sub_months(Months::new(count))? + Duration::days(1),
+        "year" | "years" => today.checked_sub_months(Months::new(count * 12))? + Duration::days(1),
+        _ => return None,
+    };
+    Some((from, today))
+}
+
+fn week_of(date: NaiveDate, week_start: Weekday) -> (NaiveDate, NaiveDate) {
+    let days_since_start =
+        (7 + date.weekday().num_days_from_monday() - week_start.num_days_from_monday()) % 7;
+    let from = date - Duration::days(days_since_start as i64);
+    (from, from + Duration::days(6))
+}
+
+/// Label of the week of the date like `2024-W18`, weeks not starting on monday
+/// are no ISO weeks and labeled by their first and last day
+pub fn week_label(date: NaiveDate, week_start: Weekday) -> String {
+    if week_start == Weekday::Mon {
+        let week = date.iso_week();
+        return format!("{}-W{:02}", week.year(), week.week());
+    }
+    let (from, to) = week_of(date, week_start);
+    format!("{} - {}", from.format("%Y-%m-%d"), to.format("%Y-%m-%d"))
+}
+
+fn month_of(date: NaiveDate) -> Option<(NaiveDate, NaiveDate)> {
+    let from = date.with_day(1)?;
+    let to = from.checked_add_months(Months::new(1))? - Duration::days(1);
+    Some((from, to))
+}
+
+fn quarter(date: NaiveDate) -> u32 {
+    date.month0() / 3 + 1
+}
+
+fn quarter_of(year: i32, quarter: u32) -> Option<(NaiveDate, NaiveDate)> {
+    if !(1..=4).contains(&quarter) {
+        return None;
+    }
+    let from = NaiveDate::from_ymd_opt(year, (quarter - 1) * 3 + 1, 1)?;
+    let to = from.checked_add_months(Months::new(3))? - Duration::days(1);
+    Some((from, to))
+}
+
+fn year_of(year: i32) -> Option<(NaiveDate, NaiveDate)> {
+    Some((
+        NaiveDate::from_ymd_opt(year, 1, 1)?,
+        NaiveDate::from_ymd_opt(year, 12, 31)?,
+    ))
+}
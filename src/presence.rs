@@ -0,0 +1,56 @@
+use std::collections::BTreeMap;
+use std::error::Error;
+
+use chrono::{NaiveDate, NaiveTime, Utc};
+
+use crate::config::AppConfig;
+use crate::duration::Duration;
+use crate::moco::client::MocoClient;
+use crate::moco::model::Presence;
+
+/// Current time of the configured zone, open presences of today last until then
+pub fn now(config: &AppConfig) -> NaiveTime {
+    Utc::now().with_timezone(&config.timezone()).time()
+}
+
+/// Presence of the day, open presences of today count until now
+pub fn totals_by_date(
+    presences: &[Presence],
+    today: NaiveDate,
+    now: NaiveTime,
+) -> BTreeMap<String, Duration> {
+    let today = today.format("%Y-%m-%d").to_string();
+    let mut totals: BTreeMap<String, Duration> = BTreeMap::new();
+    for presence in presences {
+        let now = (presence.date == today).then_some(now);
+        let total = totals.entry(presence.date.clone()).or_default();
+        *total = *total + presence.duration(now);
+    }
+    totals
+}
+
+pub async fn today(
+    moco_client: &MocoClient,
+    config: &AppConfig,
+) -> Result<Vec<Presence>, Box<dyn Error>> {
+    let today = config.today().format("%Y-%m-%d").to_string();
+    moco_client.get_presences(today.clone(), today).await
+}
+
+/// Clocks in when the first timer of the day starts, if `auto_clock_in` is enabled
+pub async fn clock_in_on_first_timer(moco_client: &MocoClient, config: &AppConfig) {
+    if !config.auto_clock_in.unwrap_or_default() {
+        return;
+    }
+
+    let clock_in = async {
+        if today(moco_client, config).await?.is_empty() {
+            let presence = moco_client.touch_presence().await?;
+            println!("Clocked in at {}", presence.from);
+        }
+        Ok::<(), Box<dyn Error>>(())
+    };
+    if let Err(e) = clock_in.await {
+        log::warn!("Could not clock in: {}", e);
+    }
+}
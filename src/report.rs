@@ -0,0 +1,275 @@
+use std::collections::BTreeMap;
+
+use chrono::{NaiveDate, Weekday};
+use serde_json::{Map, Value};
+
+use crate::cli::GroupBy;
+use crate::duration::{Duration, DurationFormat};
+use crate::moco::model::{Activity, ActivityQuery};
+use crate::period::week_label;
+
+/// Filters of `list`, ids are sent to Moco while names and the remaining
+/// fields are filtered on the client side
+#[derive(Debug, Default)]
+pub struct ActivityFilter {
+    pub project: Option<String>,
+    pub task: Option<String>,
+    pub customer: Option<String>,
+    pub billable: Option<bool>,
+    pub billed: Option<bool>,
+    pub remote_service: Option<String>,
+    pub search: Option<String>,
+}
+
+impl ActivityFilter {
+    pub fn query(&self) -> ActivityQuery {
+        let id = |value: &Option<String>| value.as_ref().and_then(|v| v.parse::<i64>().ok());
+
+        ActivityQuery {
+            project_id: id(&self.project),
+            task_id: id(&self.task),
+            company_id: id(&self.customer),
+            term: self.search.clone(),
+        }
+    }
+
+    pub fn matches(&self, activity: &Activity) -> bool {
+        let name_matches = |filter: &Option<String>, id: i64, name: &str| {
+            filter.as_ref().is_none_or(|filter| {
+                filter.parse::<i64>().ok() == Some(id) || filter.eq_ignore_ascii_case(name)
+            })
+        };
+
+        name_matches(&self.project, activity.project.id, &activity.project.name)
+            && name_matches(&self.task, activity.task.id, &activity.task.name)
+            && name_matches(
+                &self.customer,
+                activity.customer.id,
+                &activity.customer.name,
+            )
+            && self.billable.is_none_or(|b| activity.billable == b)
+            && self.billed.is_none_or(|b| activity.billed == b)
+            && self.remote_service.as_ref().is_none_or(|service| {
+                activity
+                    .remote_service
+                    .as_ref()
+                    .is_some_and(|s| s.eq_ignore_ascii_case(service))
+            })
+    }
+}
+
+/// Rows of a grouped activity list including subtotals per group
+pub struct GroupedList {
+    pub header: Vec<String>,
+    pub rows: Vec<Vec<String>>,
+    pub footer: Vec<String>,
+    pub records: Vec<Value>,
+}
+
+pub fn group_name(group_by: GroupBy) -> &'static str {
+    match group_by {
+        GroupBy::Date => "Date",
+        GroupBy::Week => "Week",
+        GroupBy::Project => "Project",
+        GroupBy::Customer => "Customer",
+        GroupBy::Task => "Task",
+        GroupBy::Billable => "Billable",
+    }
+}
+
+pub fn group_key(activity: &Activity, group_by: GroupBy, week_start: Weekday) -> String {
+    match group_by {
+        GroupBy::Date => activity.date.clone(),
+        GroupBy::Week => NaiveDate::parse_from_str(&activity.date, "%Y-%m-%d")
+            .map(|date| week_label(date, week_start))
+            .unwrap_or_default(),
+        GroupBy::Project => activity.project.name.clone(),
+        GroupBy::Customer => activity.customer.name.clone(),
+        GroupBy::Task => activity.task.name.clone(),
+        GroupBy::Billable => if activity.billable {
+            "billable"
+        } else {
+            "not billable"
+        }
+        .to_string(),
+    }
+}
+
+/// Groups the activities by the given levels, every group is followed by a
+/// subtotal row. Compact lists only contain the subtotal rows
+pub fn group_activities(
+    activities: &[Activity],
+    group_by: &[GroupBy],
+    compact: bool,
+    duration_format: DurationFormat,
+    week_start: Weekday,
+) -> GroupedList {
+    let mut header: Vec<String> = group_by
+        .iter()
+        .map(|group_by| group_name(*group_by).to_string())
+        .collect();
+    header.push("Duration".to_string());
+    if !compact {
+        header.extend(
+            ["Date", "Customer", "Task", "Description"]
+                .iter()
+                .map(|str| str.to_string()),
+        );
+    }
+
+    let mut list = GroupedList {
+        footer: header
+            .iter()
+            .enumerate()
+            .map(|(index, _)| {
+                if index == group_by.len() {
+                    activities
+                        .iter()
+                        .map(Activity::duration)
+                        .sum::<Duration>()
+                        .format(duration_format)
+                } else {
+                    "-".to_string()
+                }
+            })
+            .collect(),
+        header,
+        rows: vec![],
+        records: vec![],
+    };
+
+    append_groups(
+        activities.iter().collect(),
+        group_by,
+        &mut vec![],
+        compact,
+        duration_format,
+        week_start,
+        &mut list,
+    );
+
+    list
+}
+
+fn append_groups(
+    activities: Vec<&Activity>,
+    group_by: &[GroupBy],
+    keys: &mut Vec<String>,
+    compact: bool,
+    duration_format: DurationFormat,
+    week_start: Weekday,
+    list: &mut GroupedList,
+) {
+    let level = group_by[keys.len()];
+
+    let mut groups: BTreeMap<String, Vec<&Activity>> = BTreeMap::new();
+    for activity in activities {
+        groups
+            .entry(group_key(activity, level, week_start))
+            .or_default()
+            .push(activity);
+    }
+
+    for (key, activities) in groups {
+        keys.push(key);
+
+        let duration: Duration = activities.iter().map(|a| a.duration()).sum();
+
+        if keys.len() < group_by.len() {
+            append_groups(
+                activities,
+                group_by,
+                keys,
+                compact,
+                duration_format,
+                week_start,
+                list,
+            );
+        } else if !compact {
+            for activity in activities {
+                let mut row = keys.clone();
+                row.push(activity.duration().format(duration_format));
+                row.extend([
+                    activity.date.clone(),
+                    activity.customer.name.clone(),
+                    activity.task.name.clone(),
+                    activity.description.clone().unwrap_or_default(),
+                ]);
+                list.rows.push(row);
+            }
+        }
+
+        let mut row = keys.clone();
+        row.resize(group_by.len(), String::new());
+        row.push(duration.format(duration_format));
+        if !compact {
+            row.push("Subtotal".to_string());
+            row.resize(row.len() + 3, String::new());
+        }
+        list.rows.push(row);
+
+        let mut record = Map::new();
+        for (group_by, key) in group_by.iter().zip(keys.iter()) {
+            record.insert(
+                group_name(*group_by).to_lowercase(),
+                Value::String(key.clone()),
+            );
+        }
+        record.insert("hours".to_string(), duration.hours().into());
+        record.insert("seconds".to_string(), duration.seconds().into());
+        list.records.push(Value::Object(record));
+
+        keys.pop();
+    }
+}
+
+/// Adds the presence of each day next to the booked duration of a list
+/// grouped by date, days with a presence but no bookings get a row too
+pub fn add_presence(
+    list: &mut GroupedList,
+    presences: &BTreeMap<String, Duration>,
+    duration_format: DurationFormat,
+) {
+    for date in presences.keys() {
+        if !list.rows.iter().any(|row| row[0] == *date) {
+            list.rows.push(vec![
+                date.clone(),
+                Duration::default().format(duration_format),
+            ]);
+            let mut record = Map::new();
+            record.insert("date".to_string(), Value::String(date.clone()));
+            record.insert("hours".to_string(), 0.0.into());
+            record.insert("seconds".to_string(), 0.into());
+            list.records.push(Value::Object(record));
+        }
+    }
+    list.rows.sort_by(|a, b| a[0].cmp(&b[0]));
+    list.records
+        .sort_by(|a, b| a["date"].as_str().cmp(&b["date"].as_str()));
+
+    list.header.insert(2, "Presence".to_string());
+    for row in list.rows.iter_mut() {
+        let presence = presences.get(&row[0]).copied().unwrap_or_default();
+        row.insert(2, presence.format(duration_format));
+    }
+    list.footer.insert(
+        2,
+        presences
+            .values()
+            .copied()
+            .sum::<Duration>()
+            .format(duration_format),
+    );
+    for record in list.records.iter_mut() {
+        if let Some(record) = record.as_object_mut() {
+            let presence = record
+                .get("date")
+                .and_then(Value::as_str)
+                .and_then(|date| presences.get(date))
+                .copied()
+                .unwrap_or_default();
+            record.insert("presence_hours".to_string(), presence.hours().into());
+            record.insert("presence_seconds".to_string(), presence.seconds().into());
+        }
+    }
+}
@@ -0,0 +1,319 @@
+use std::error::Error;
+use std::fs::{read_to_string, remove_file};
+
+use chrono::{DateTime, NaiveDate, Utc};
+use chrono_tz::Tz;
+use serde::{Deserialize, Serialize};
+
+use crate::config::AppConfig;
+use crate::duration::{Duration, DurationFormat};
+use crate::moco::client::MocoClient;
+use crate::moco::model::CreateActivity;
+use crate::utils::{
+    ask_question, input_enabled, optional_validator, prompt_task_select, stable_hash,
+};
+
+use super::{format_start, render_template, state_path, write_state};
+
+const TIMER_FILE: &str = "timer.json";
+const QUEUE_FILE: &str = "queue.json";
+const REMOTE_SERVICE: &str = "mococli_timer";
+
+/// Timer kept in a file of the config dir, it works offline and can be paused
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct LocalTimer {
+    pub date: String,
+    pub project_id: i64,
+    pub project: String,
+    pub task_id: i64,
+    pub task: String,
+    pub description: String,
+    pub notes: Vec<String>,
+    pub started_at: DateTime<Utc>,
+    /// Seconds of the intervals before the last pause
+    pub seconds: i64,
+    /// Start of the current interval, none while paused
+    pub resumed_at: Option<DateTime<Utc>>,
+}
+
+impl LocalTimer {
+    pub fn new(
+        date: NaiveDate,
+        (project_id, project): (i64, String),
+        (task_id, task): (i64, String),
+        description: String,
+    ) -> Self {
+        let now = Utc::now();
+        LocalTimer {
+            date: date.format("%Y-%m-%d").to_string(),
+            project_id,
+            project,
+            task_id,
+            task,
+            description,
+            notes: vec![],
+            started_at: now,
+            seconds: 0,
+            resumed_at: Some(now),
+        }
+    }
+
+    pub fn load() -> Result<Option<LocalTimer>, Box<dyn Error>> {
+        let path = state_path(TIMER_FILE)?;
+        if !path.exists() {
+            return Ok(None);
+        }
+        Ok(Some(serde_json::from_str(&read_to_string(path)?)?))
+    }
+
+    pub fn save(&self) -> Result<(), Box<dyn Error>> {
+        write_state(TIMER_FILE, &serde_json::to_string(self)?)
+    }
+
+    pub fn remove() -> Result<(), Box<dyn Error>> {
+        let path = state_path(TIMER_FILE)?;
+        if path.exists() {
+            remove_file(path)?;
+        }
+        Ok(())
+    }
+
+    pub fn is_paused(&self) -> bool {
+        self.resumed_at.is_none()
+    }
+
+    pub fn elapsed(&self, now: DateTime<Utc>) -> Duration {
+        let running = self
+            .resumed_at
+            .map(|resumed_at| (now - resumed_at).num_seconds())
+            .unwrap_or(0);
+        Duration::from_seconds(self.seconds + running)
+    }
+
+    pub fn pause(&mut self, now: DateTime<Utc>) {
+        self.seconds = self.elapsed(now).seconds();
+        self.resumed_at = None;
+    }
+
+    pub fn resume(&mut self, now: DateTime<Utc>) {
+        if self.resumed_at.is_none() {
+            self.resumed_at = Some(now);
+        }
+    }
+
+    /// Description followed by the annotations
+    pub fn full_description(&self) -> String {
+        std::iter::once(&self.description)
+            .chain(self.notes.iter())
+            .filter(|text| !text.is_empty())
+            .cloned()
+            .collect::<Vec<String>>()
+            .join("; ")
+    }
+
+    /// The remote id identifies the booking, so a retried booking which
+    /// already reached Moco is not booked twice
+    pub fn booking(&self, duration: Duration) -> CreateActivity {
+        CreateActivity {
+            date: self.date.clone(),
+            project_id: self.project_id,
+            task_id: self.task_id,
+            seconds: Some(duration.seconds()),
+            description: self.full_description(),
+            remote_service: Some(REMOTE_SERVICE.to_string()),
+            remote_id: Some(stable_hash(&format!(
+                "{}|{}",
+                self.started_at.to_rfc3339(),
+                self.description
+            ))),
+            ..Default::default()
+        }
+    }
+
+    pub fn render(
+        &self,
+        template: &str,
+        timezone: &Tz,
+        today: NaiveDate,
+        duration_format: DurationFormat,
+    ) -> String {
+        render_template(
+            template,
+            &[
+                ("{id}", String::new()),
+                ("{date}", self.date.clone()),
+                ("{project}", self.project.clone()),
+                ("{task}", self.task.clone()),
+                ("{customer}", String::new()),
+                ("{description}", self.full_description()),
+                ("{start}", format_start(self.started_at, timezone, today)),
+                (
+                    "{elapsed}",
+                    self.elapsed(Utc::now()).format(duration_format),
+                ),
+                (
+                    "{state}",
+                    if self.is_paused() {
+                        "paused"
+                    } else {
+                        "running"
+                    }
+                    .to_string(),
+                ),
+            ],
+        )
+    }
+}
+
+/// Local timer of the task, only the ids are known if Moco can not be reached
+pub async fn select(
+    moco_client: &MocoClient,
+    project: Option<i64>,
+    task: Option<i64>,
+    description: Option<String>,
+    today: NaiveDate,
+) -> Result<LocalTimer, Box<dyn Error>> {
+    let (project, task) = match prompt_task_select(moco_client, project, task, None).await {
+        Ok((p, t)) => ((p.id, p.name), (t.id, t.name)),
+        Err(e) if is_offline(e.as_ref()) => match (project, task) {
+            (Some(project), Some(task)) => {
+                ((project, project.to_string()), (task, task.to_string()))
+            }
+            _ => return Err("Moco is not reachable, use --project and --task".into()),
+        },
+        Err(e) => return Err(e),
+    };
+
+    let description = match description {
+        Some(description) => description,
+        None if !input_enabled() => String::new(),
+        None => ask_question("Description: ", &optional_validator)?,
+    };
+
+    Ok(LocalTimer::new(today, project, task, description))
+}
+
+/// Books the accumulated time of the local timer and removes it
+pub async fn stop(
+    moco_client: &MocoClient,
+    mut timer: LocalTimer,
+    config: &AppConfig,
+) -> Result<(), Box<dyn Error>> {
+    timer.pause(Utc::now());
+
+    let duration_format = config.duration_format();
+    let duration = timer.elapsed(Utc::now());
+    let rounded = config.round(duration);
+    if rounded == duration {
+        println!("Activity duration: {}", duration.format(duration_format));
+    } else {
+        println!(
+            "Activity duration: {} (rounded from {})",
+            rounded.format(duration_format),
+            duration.format(duration_format)
+        );
+    }
+
+    book(moco_client, timer.booking(rounded)).await?;
+    LocalTimer::remove()
+}
+
+/// Errors of requests which did not reach Moco, e.g. without network
+pub fn is_offline(error: &(dyn Error + 'static)) -> bool {
+    error
+        .downcast_ref::<reqwest::Error>()
+        .is_some_and(|e| e.is_connect() || e.is_timeout())
+}
+
+/// Creates the activity, or queues it if Moco can not be reached
+pub async fn book(
+    moco_client: &MocoClient,
+    activity: CreateActivity,
+) -> Result<(), Box<dyn Error>> {
+    match moco_client.create_activity(&activity).await {
+        Ok(_) => Ok(()),
+        Err(e) if is_offline(e.as_ref()) => {
+            let mut queue = load_queue()?;
+            queue.push(activity);
+            write_state(QUEUE_FILE, &serde_json::to_string(&queue)?)?;
+            println!("Moco is not reachable, the booking is queued until the next command");
+            Ok(())
+        }
+        Err(e) => Err(e),
+    }
+}
+
+/// Delivers queued bookings, skipping the ones Moco already has (e.g. after a
+/// timeout). Rejected bookings are dropped, the ones still failing stay queued
+pub async fn deliver_queue(moco_client: &MocoClient) -> Result<(), Box<dyn Error>> {
+    let queue = load_queue()?;
+    if queue.is_empty() {
+        return Ok(());
+    }
+
+    let from = queue
+        .iter()
+        .map(|a| a.date.clone())
+        .min()
+        .unwrap_or_default();
+    let to = queue
+        .iter()
+        .map(|a| a.date.clone())
+        .max()
+        .unwrap_or_default();
+    let existing = match moco_client.get_activities(from, to, None, None).await {
+        Ok(existing) => existing,
+        Err(e) if is_offline(e.as_ref()) => return Ok(()),
+        Err(e) => return Err(e),
+    };
+
+    let mut remaining = vec![];
+    for activity in queue {
+        let delivered = existing.iter().any(|existing| {
+            activity.remote_id.is_some()
+                && existing.remote_id == activity.remote_id
+                && existing.remote_service == activity.remote_service
+        });
+        if delivered {
+            continue;
+        }
+
+        match moco_client.create_activity(&activity).await {
+            Ok(_) => eprintln!(
+                "Delivered queued booking of {} ({})",
+                activity.date, activity.description
+            ),
+            Err(e) if is_offline(e.as_ref()) => remaining.push(activity),
+            Err(e) if is_rejected(e.as_ref()) => eprintln!(
+                "Dropped queued booking of {} ({}), Moco rejected it: {}",
+                activity.date, activity.description, e
+            ),
+            Err(e) => {
+                eprintln!(
+                    "Could not deliver queued booking of {} ({}), retrying with the next command: {}",
+                    activity.date, activity.description, e
+                );
+                remaining.push(activity);
+            }
+        }
+    }
+
+    write_state(QUEUE_FILE, &serde_json::to_string(&remaining)?)
+}
+
+/// Invalid bookings, retrying them can't succeed. Authentication errors are
+/// retried as they go away after a new login
+fn is_rejected(error: &(dyn Error + 'static)) -> bool {
+    error
+        .downcast_ref::<reqwest::Error>()
+        .and_then(|e| e.status())
+        .is_some_and(|status| matches!(status.as_u16(), 400 | 404 | 422))
+}
+
+fn load_queue() -> Result<Vec<CreateActivity>, Box<dyn Error>> {
+    let path = state_path(QUEUE_FILE)?;
+    if !path.exists() {
+        return Ok(vec![]);
+    }
+    Ok(serde_json::from_str(&read_to_string(path)?)?)
+}
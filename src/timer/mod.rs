@@ -0,0 +1,395 @@
+use std::error::Error;
+use std::fs::{create_dir_all, write};
+use std::path::PathBuf;
+
+pub mod local;
+pub mod status;
+
+use chrono::{DateTime, Days, NaiveDate, Utc};
+use chrono_tz::Tz;
+use serde::{Deserialize, Serialize};
+
+use crate::config::{get_state_path, AppConfig};
+use crate::duration::{Duration, DurationFormat};
+use crate::moco::client::MocoClient;
+use crate::moco::model::{
+    Activity, ActivityQuery, ControlActivityTimer, CreateActivity, EditActivity, GetActivity,
+};
+use crate::utils::{
+    ask_question, input_enabled, optional_validator, prompt_task_select, render_list_select,
+    require_input,
+};
+
+/// Days searched for a running timer, forgotten timers keep running over weekends and holidays
+const LOOKBACK_DAYS: u64 = 14;
+
+/// Activity with a running Moco timer
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RunningTimer {
+    pub activity: Activity,
+    pub started_at: DateTime<Utc>,
+}
+
+impl RunningTimer {
+    /// Booked time of the activity plus the time since the timer was started
+    pub fn elapsed(&self, now: DateTime<Utc>) -> Duration {
+        self.activity.duration() + self.running_since(now)
+    }
+
+    pub fn running_since(&self, now: DateTime<Utc>) -> Duration {
+        Duration::from_seconds((now - self.started_at).num_seconds())
+    }
+
+    pub fn start(&self, timezone: &Tz, today: NaiveDate) -> String {
+        format_start(self.started_at, timezone, today)
+    }
+
+    pub fn render(
+        &self,
+        template: &str,
+        timezone: &Tz,
+        today: NaiveDate,
+        duration_format: DurationFormat,
+    ) -> String {
+        let activity = &self.activity;
+        render_template(
+            template,
+            &[
+                ("{id}", activity.id.to_string()),
+                ("{date}", activity.date.clone()),
+                ("{project}", activity.project.name.clone()),
+                ("{task}", activity.task.name.clone()),
+                ("{customer}", activity.customer.name.clone()),
+                (
+                    "{description}",
+                    activity.description.clone().unwrap_or_default(),
+                ),
+                ("{start}", self.start(timezone, today)),
+                (
+                    "{elapsed}",
+                    self.elapsed(Utc::now()).format(duration_format),
+                ),
+                ("{state}", "running".to_string()),
+            ],
+        )
+    }
+}
+
+/// Start time in the given zone, with date if the timer was not started today
+pub fn format_start(started_at: DateTime<Utc>, timezone: &Tz, today: NaiveDate) -> String {
+    let started_at = started_at.with_timezone(timezone);
+    if started_at.date_naive() == today {
+        started_at.format("%H:%M").to_string()
+    } else {
+        started_at.format("%Y-%m-%d %H:%M").to_string()
+    }
+}
+
+/// Replaces `{id}`, `{date}`, `{project}`, `{task}`, `{customer}`,
+/// `{description}`, `{start}`, `{elapsed}` and `{state}` in the template.
+/// Values are inserted in one pass, so placeholders inside them stay as they are
+pub fn render_template(template: &str, values: &[(&str, String)]) -> String {
+    let mut rendered = String::new();
+    let mut rest = template;
+    while let Some(index) = rest.find('{') {
+        rendered.push_str(&rest[..index]);
+        rest = &rest[index..];
+        match values
+            .iter()
+            .find(|(placeholder, _)| rest.starts_with(placeholder))
+        {
+            Some((placeholder, value)) => {
+                rendered.push_str(value);
+                rest = &rest[placeholder.len()..];
+            }
+            None => {
+                rendered.push('{');
+                rest = &rest[1..];
+            }
+        }
+    }
+    rendered.push_str(rest);
+    rendered
+}
+
+/// Activity to start a timer on: the given or a picked one of today, or today's
+/// activity of the task and description which is created without hours if
+/// there is none yet
+pub async fn select_activity(
+    moco_client: &MocoClient,
+    activity: Option<i64>,
+    project: Option<i64>,
+    task: Option<i64>,
+    description: Option<String>,
+    today: NaiveDate,
+    duration_format: DurationFormat,
+) -> Result<Activity, Box<dyn Error>> {
+    if let Some(activity_id) = activity {
+        return moco_client.get_activity(&GetActivity { activity_id }).await;
+    }
+
+    let date = today.format("%Y-%m-%d").to_string();
+    if project.is_none() && task.is_none() && description.is_none() && input_enabled() {
+        // Moco only runs timers of today's activities
+        let mut activities: Vec<Option<Activity>> = moco_client
+            .get_activities(date.clone(), date.clone(), None, None)
+            .await?
+            .into_iter()
+            .map(Some)
+            .collect();
+        if !activities.is_empty() {
+            activities.push(None);
+            let index = render_list_select(
+                &activities,
+                vec!["Index", "Duration", "Project", "Task", "Description"],
+                "Choose your Activity: ",
+                None,
+                &(|(index, activity)| match activity {
+                    Some(activity) => vec![
+                        index.to_string(),
+                        activity.duration().format(duration_format),
+                        activity.project.name.clone(),
+                        activity.task.name.clone(),
+                        activity.description.clone().unwrap_or_default(),
+                    ],
+                    None => vec![
+                        index.to_string(),
+                        String::new(),
+                        "New activity".to_string(),
+                        String::new(),
+                        String::new(),
+                    ],
+                }),
+            )?;
+            if let Some(activity) = activities.swap_remove(index) {
+                return Ok(activity);
+            }
+        }
+    }
+
+    let (project, task) = prompt_task_select(moco_client, project, task, None).await?;
+    let description = match description {
+        Some(description) => description,
+        None if !input_enabled() => String::new(),
+        None => ask_question("Description: ", &optional_validator)?,
+    };
+
+    let existing = moco_client
+        .query_activities(
+            date.clone(),
+            date.clone(),
+            &ActivityQuery {
+                task_id: Some(task.id),
+                ..Default::default()
+            },
+        )
+        .await?
+        .into_iter()
+        .find(|activity| {
+            activity.project.id == project.id
+                && activity.description.as_deref().unwrap_or_default() == description
+        });
+
+    if let Some(activity) = existing {
+        println!("Continuing today's activity {}", activity.id);
+        return Ok(activity);
+    }
+
+    moco_client
+        .create_activity(&CreateActivity {
+            date,
+            project_id: project.id,
+            task_id: task.id,
+            seconds: Some(0),
+            description,
+            ..Default::default()
+        })
+        .await
+}
+
+pub async fn start(moco_client: &MocoClient, activity: &Activity) -> Result<(), Box<dyn Error>> {
+    moco_client
+        .control_activity_timer(&ControlActivityTimer {
+            control: "start".to_string(),
+            activity_id: activity.id,
+        })
+        .await?;
+    status::invalidate();
+    Ok(())
+}
+
+/// Stops the timer, rounds the final duration and reports it
+pub async fn stop(
+    moco_client: &MocoClient,
+    activity: &Activity,
+    config: &AppConfig,
+) -> Result<(), Box<dyn Error>> {
+    moco_client
+        .control_activity_timer(&ControlActivityTimer {
+            control: "stop".to_string(),
+            activity_id: activity.id,
+        })
+        .await?;
+
+    let a = moco_client
+        .get_activity(&GetActivity {
+            activity_id: activity.id,
+        })
+        .await?;
+    status::stopped(&a);
+    let duration_format = config.duration_format();
+    let rounded = config.round(a.duration());
+    if rounded == a.duration() {
+        println!(
+            "Activity duration: {}",
+            a.duration().format(duration_format)
+        );
+    } else {
+        moco_client
+            .edit_activity(&EditActivity {
+                activity_id: a.id,
+                project_id: a.project.id,
+                task_id: a.task.id,
+                date: a.date.clone(),
+                description: a.description.clone().unwrap_or_default(),
+                seconds: rounded.seconds(),
+                ..Default::default()
+            })
+            .await?;
+        println!(
+            "Activity duration: {} (rounded from {})",
+            rounded.format(duration_format),
+            a.duration().format(duration_format)
+        );
+    }
+
+    Ok(())
+}
+
+pub async fn find_running(
+    moco_client: &MocoClient,
+    today: NaiveDate,
+) -> Result<Option<RunningTimer>, Box<dyn Error>> {
+    let from = today - Days::new(LOOKBACK_DAYS);
+    let activities = moco_client
+        .get_activities(
+            from.format("%Y-%m-%d").to_string(),
+            today.format("%Y-%m-%d").to_string(),
+            None,
+            None,
+        )
+        .await?;
+
+    Ok(activities.into_iter().find_map(|activity| {
+        let started_at = activity
+            .timer_started_at
+            .as_str()
+            .and_then(|started_at| DateTime::parse_from_rfc3339(started_at).ok())?
+            .with_timezone(&Utc);
+        Some(RunningTimer {
+            activity,
+            started_at,
+        })
+    }))
+}
+
+/// Warns on stderr about a timer running longer than the configured threshold,
+/// failures are only logged to not break the actual command
+pub async fn warn_forgotten(moco_client: &MocoClient, config: &AppConfig) {
+    let Some(threshold) = config.timer_warning() else {
+        return;
+    };
+
+    match find_running(moco_client, config.today()).await {
+        Ok(Some(running)) if running.running_since(Utc::now()) > threshold => {
+            eprintln!(
+                "Warning: the timer of \"{} / {}\" is running since {}, fix it with \"timer fix\"",
+                running.activity.project.name,
+                running.activity.task.name,
+                running.start(&config.timezone(), config.today()),
+            );
+        }
+        Ok(_) => {}
+        Err(e) => log::debug!("Could not check for running timers: {}", e),
+    }
+}
+
+/// Stops the timer and books the real duration, e.g. of a timer forgotten overnight
+pub async fn fix(
+    moco_client: &MocoClient,
+    running: &RunningTimer,
+    hours: Option<Duration>,
+    config: &AppConfig,
+) -> Result<(), Box<dyn Error>> {
+    let duration_format = config.duration_format();
+    println!(
+        "Timer of \"{} / {}\" on {} is running since {}",
+        running.activity.project.name,
+        running.activity.task.name,
+        running.activity.date,
+        running.start(&config.timezone(), config.today()),
+    );
+
+    let duration = match hours {
+        Some(hours) => hours,
+        None => {
+            require_input("--hours")?;
+            let answer = ask_question(
+                &format!(
+                    "Real duration - Default '{}': ",
+                    running.elapsed(Utc::now()).format(duration_format)
+                ),
+                &|answer| {
+                    if answer.is_empty() {
+                        None
+                    } else {
+                        answer.parse::<Duration>().err()
+                    }
+                },
+            )?;
+            if answer.is_empty() {
+                running.elapsed(Utc::now())
+            } else {
+                answer.parse::<Duration>()?
+            }
+        }
+    };
+
+    moco_client
+        .control_activity_timer(&ControlActivityTimer {
+            control: "stop".to_string(),
+            activity_id: running.activity.id,
+        })
+        .await?;
+    status::stopped(&running.activity);
+
+    let activity = &running.activity;
+    let rounded = config.round(duration);
+    moco_client
+        .edit_activity(&EditActivity {
+            activity_id: activity.id,
+            project_id: activity.project.id,
+            task_id: activity.task.id,
+            date: activity.date.clone(),
+            description: activity.description.clone().unwrap_or_default(),
+            seconds: rounded.seconds(),
+            ..Default::default()
+        })
+        .await?;
+    println!("Activity duration: {}", rounded.format(duration_format));
+
+    Ok(())
+}
+
+fn state_path(name: &str) -> Result<PathBuf, Box<dyn Error>> {
+    get_state_path(name).ok_or_else(|| "Can't find os config directory".into())
+}
+
+fn write_state(name: &str, content: &str) -> Result<(), Box<dyn Error>> {
+    let path = state_path(name)?;
+    if let Some(parent) = path.parent() {
+        create_dir_all(parent)?;
+    }
+    write(path, content)?;
+    Ok(())
+}
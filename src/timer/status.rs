@@ -0,0 +1,120 @@
+use std::error::Error;
+use std::fs::read_to_string;
+
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+
+use crate::cli::Bar;
+use crate::config::AppConfig;
+use crate::moco::client::MocoClient;
+use crate::moco::model::Activity;
+
+use super::local::{is_offline, LocalTimer};
+use super::{find_running, state_path, write_state, RunningTimer};
+
+const STATUS_FILE: &str = "status.json";
+
+/// Moco timer as seen by the last refresh, so status bars don't call the API
+/// on every redraw
+#[derive(Serialize, Deserialize, Debug, Default)]
+pub struct StatusCache {
+    pub checked_at: Option<DateTime<Utc>>,
+    pub running: Option<RunningTimer>,
+    /// Activity of the last stopped timer, the target of `timer switch --previous`
+    pub previous: Option<Activity>,
+}
+
+impl StatusCache {
+    pub fn load() -> StatusCache {
+        state_path(STATUS_FILE)
+            .ok()
+            .and_then(|path| read_to_string(path).ok())
+            .and_then(|content| serde_json::from_str(&content).ok())
+            .unwrap_or_default()
+    }
+
+    pub fn save(&self) -> Result<(), Box<dyn Error>> {
+        write_state(STATUS_FILE, &serde_json::to_string(self)?)
+    }
+}
+
+/// Forces a refresh on the next redraw, e.g. after a timer was started
+pub fn invalidate() {
+    let mut cache = StatusCache::load();
+    cache.checked_at = None;
+    if let Err(e) = cache.save() {
+        log::debug!("Could not save timer status: {}", e);
+    }
+}
+
+/// Remembers the activity of a stopped timer for `timer switch --previous`
+pub fn stopped(activity: &Activity) {
+    let cache = StatusCache {
+        checked_at: None,
+        running: None,
+        previous: Some(activity.clone()),
+    };
+    if let Err(e) = cache.save() {
+        log::debug!("Could not save timer status: {}", e);
+    }
+}
+
+/// Running Moco timer, refreshed at most once per `status_refresh`. Without
+/// network the cached timer is kept
+pub async fn cached_running(
+    moco_client: &MocoClient,
+    config: &AppConfig,
+) -> Result<Option<RunningTimer>, Box<dyn Error>> {
+    let mut cache = StatusCache::load();
+    let now = Utc::now();
+    let fresh = cache.checked_at.is_some_and(|checked_at| {
+        now - checked_at < chrono::Duration::seconds(config.status_refresh().seconds())
+    });
+    if fresh {
+        return Ok(cache.running);
+    }
+
+    match find_running(moco_client, config.today()).await {
+        Ok(running) => {
+            cache.checked_at = Some(now);
+            cache.running = running;
+            cache.save()?;
+        }
+        Err(e) if is_offline(e.as_ref()) => log::debug!("Keeping cached timer status: {}", e),
+        Err(e) => return Err(e),
+    }
+    Ok(cache.running)
+}
+
+/// Status line of the running (local) timer for the status bar
+pub fn render(
+    bar: Bar,
+    text: Option<String>,
+    tooltip: Option<String>,
+    local_timer: Option<&LocalTimer>,
+) -> String {
+    let class = match (local_timer, &text) {
+        (Some(local_timer), _) if local_timer.is_paused() => "paused",
+        (_, Some(_)) => "running",
+        (_, None) => "stopped",
+    };
+    let text = text.unwrap_or_default();
+
+    match bar {
+        Bar::Plain => text,
+        Bar::Waybar => json!({
+            "text": text,
+            "tooltip": tooltip.unwrap_or_default(),
+            "class": class,
+            "alt": class,
+        })
+        .to_string(),
+        Bar::I3blocks => json!({
+            "name": "mococli",
+            "full_text": text,
+            "urgent": class == "paused",
+        })
+        .to_string(),
+    }
+}
@@ -1,13 +1,53 @@
 use std::error::Error;
-use std::io::Write;
-use std::ops::Sub;
+use std::io::{IsTerminal, Write};
+use std::sync::atomic::{AtomicBool, Ordering};
 
-use chrono::{Duration, Utc};
+use chrono::NaiveDate;
 
+use serde::Serialize;
+
+use crate::cli::Output;
+use crate::config::AppConfig;
+use crate::duration::{Duration, DurationFormat};
 use crate::moco::client::MocoClient;
-use crate::moco::model::{Activity, Project, ProjectTask};
+use crate::moco::model::{
+    Activity, CreateActivity, EditActivity, GetActivity, Project, ProjectTask,
+};
+
+static NO_INPUT: AtomicBool = AtomicBool::new(false);
+
+/// Turns prompts into errors, for scripts and CI jobs (`--no-input`)
+pub fn disable_input() {
+    NO_INPUT.store(true, Ordering::Relaxed);
+}
+
+pub fn input_enabled() -> bool {
+    !NO_INPUT.load(Ordering::Relaxed)
+}
+
+/// Fails if input is disabled, `needed` names the flag answering the prompt
+pub fn require_input(needed: &str) -> Result<(), Box<dyn Error>> {
+    if input_enabled() {
+        Ok(())
+    } else {
+        Err(format!("Missing {} (input is disabled by --no-input)", needed).into())
+    }
+}
+
+/// Asks before destructive commands, `--yes` confirms up front
+pub fn confirm(question: &str, yes: bool) -> Result<bool, Box<dyn Error>> {
+    if yes {
+        return Ok(true);
+    }
+    require_input("--yes")?;
+
+    let answer = ask_question(&format!("{} (y/N): ", question), &optional_validator)?;
+    Ok(answer.eq_ignore_ascii_case("y") || answer.eq_ignore_ascii_case("yes"))
+}
 
 pub fn read_line() -> Result<String, Box<dyn Error>> {
+    require_input("an answer")?;
+
     let mut input = String::new();
     std::io::stdin().read_line(&mut input)?;
     input.remove(input.len() - 1);
@@ -15,6 +55,20 @@ pub fn read_line() -> Result<String, Box<dyn Error>> {
 }
 
 pub fn render_table(list: Vec<Vec<String>>) {
+    write_table(&mut std::io::stdout(), list);
+}
+
+/// Prompts and their tables go to stderr when stdout is piped, so they don't
+/// mix with the machine readable output
+fn interactive_output() -> Box<dyn Write> {
+    if std::io::stdout().is_terminal() {
+        Box::new(std::io::stdout())
+    } else {
+        Box::new(std::io::stderr())
+    }
+}
+
+fn write_table(out: &mut dyn Write, list: Vec<Vec<String>>) {
     if list.is_empty() {
         return;
     }
@@ -35,107 +89,135 @@ pub fn render_table(list: Vec<Vec<String>>) {
 
     for row in list.iter() {
         for (column_index, column_content) in row.iter().enumerate() {
-            print!(
+            let _ = write!(
+                out,
                 "{}{}\t",
                 column_content,
                 " ".repeat(list_elem_max_length[column_index] - column_content.len())
-            )
+            );
         }
-        println!();
+        let _ = writeln!(out);
     }
 }
 
+/// Defaults to a table on terminals and to JSON when piped
+pub fn resolve_output(output: Option<Output>) -> Output {
+    output.unwrap_or_else(|| {
+        if std::io::stdout().is_terminal() {
+            Output::Table
+        } else {
+            Output::Json
+        }
+    })
+}
+
+/// Prints informational text to stdout, or to stderr if stdout is used for
+/// machine readable output
+pub fn print_info(output: Option<Output>, text: &str) {
+    match resolve_output(output) {
+        Output::Table | Output::Markdown => println!("{}", text),
+        _ => eprintln!("{}", text),
+    }
+}
+
+/// Renders `rows` as table, markdown or delimiter separated values, JSON
+/// formats serialize `records` instead. The `footer` (e.g. sums) is only part
+/// of the human readable formats
+pub fn render_output<T: Serialize>(
+    output: Option<Output>,
+    header: Vec<String>,
+    rows: Vec<Vec<String>>,
+    footer: Option<Vec<String>>,
+    records: &[T],
+) -> Result<(), Box<dyn Error>> {
+    match resolve_output(output) {
+        Output::Table => {
+            let mut list = vec![header];
+            list.extend(rows);
+            list.extend(footer);
+            render_table(list);
+        }
+        Output::Markdown => {
+            let escape = |cell: &String| cell.replace('|', "\\|");
+            println!(
+                "| {} |",
+                header.iter().map(escape).collect::<Vec<_>>().join(" | ")
+            );
+            println!("|{}", " --- |".repeat(header.len()));
+            for row in rows.iter().chain(footer.iter()) {
+                println!(
+                    "| {} |",
+                    row.iter().map(escape).collect::<Vec<_>>().join(" | ")
+                );
+            }
+        }
+        Output::Csv | Output::Tsv => {
+            let delimiter = if resolve_output(output) == Output::Csv {
+                b','
+            } else {
+                b'\t'
+            };
+            let mut writer = csv::WriterBuilder::new()
+                .delimiter(delimiter)
+                .from_writer(std::io::stdout());
+            writer.write_record(&header)?;
+            for row in rows {
+                writer.write_record(&row)?;
+            }
+            writer.flush()?;
+        }
+        Output::Json => println!("{}", serde_json::to_string_pretty(records)?),
+        Output::Ndjson => {
+            for record in records {
+                println!("{}", serde_json::to_string(record)?);
+            }
+        }
+    }
+    Ok(())
+}
+
 pub fn render_list_select<T>(
     list: &[T],
     headline: Vec<&str>,
     prompt: &str,
+    default: Option<usize>,
     linenderer: &dyn Fn((usize, &T)) -> Vec<String>,
 ) -> Result<usize, Box<dyn Error>> {
     loop {
-        let mut rendered_list: Vec<Vec<String>> =
-            list.iter().enumerate().map(|ele| linenderer(ele)).collect();
+        let mut rendered_list: Vec<Vec<String>> = list.iter().enumerate().map(linenderer).collect();
         rendered_list.insert(0, headline.iter().map(|x| x.to_string()).collect());
-        render_table(rendered_list);
+        let mut out = interactive_output();
+        write_table(&mut out, rendered_list);
 
-        print!("{}", prompt);
-        std::io::stdout().flush()?;
+        write!(out, "{}", prompt)?;
+        out.flush()?;
 
-        let index_input = read_line().map(|x| x.parse::<usize>().ok()).ok().flatten();
+        let line = read_line()?;
+        if let (true, Some(default)) = (line.is_empty(), default) {
+            return Ok(default);
+        }
+        let index_input = line.parse::<usize>().ok();
 
         if let Some(index) = index_input {
             if index < list.len() {
                 return Ok(index);
             }
         }
-        println!("Index invalid")
+        writeln!(out, "Index invalid")?;
     }
 }
 
-pub fn select_from_to_date(
-    today: bool,
-    week: bool,
-    last_week: bool,
-    month: bool,
-    last_month: bool,
-) -> (chrono::DateTime<Utc>, chrono::DateTime<Utc>) {
-    use now::DateTimeNow;
-
-    let now = Utc::now();
-
-    let mut from = if today { Some(now) } else { None };
-    let mut to = if today { Some(now) } else { None };
-
-    from = if week {
-        Some(now.beginning_of_week())
-    } else {
-        from
-    };
-    to = if week { Some(now.end_of_week()) } else { to };
-
-    from = if last_week {
-        Some(now.beginning_of_week().sub(Duration::weeks(1)))
-    } else {
-        from
-    };
-    to = if last_week {
-        Some(now.end_of_week().sub(Duration::weeks(1)))
-    } else {
-        to
-    };
-
-    from = if month {
-        Some(now.beginning_of_month())
-    } else {
-        from
-    };
-    to = if month { Some(now.end_of_month()) } else { to };
-
-    from = if last_month {
-        Some(now.beginning_of_month().sub(Duration::weeks(4)))
-    } else {
-        from
-    };
-    to = if last_month {
-        Some(now.end_of_month().sub(Duration::weeks(4)))
-    } else {
-        to
-    };
-
-    let from = from.unwrap_or(now);
-    let to = to.unwrap_or(now);
-    (from, to)
-}
-
 pub fn ask_question(
     question: &str,
     validator: &dyn Fn(&str) -> Option<String>,
 ) -> Result<String, Box<dyn Error>> {
+    let mut out = interactive_output();
     loop {
-        print!("{}", question);
-        std::io::stdout().flush()?;
+        write!(out, "{}", question)?;
+        out.flush()?;
         let line = read_line()?;
         if let Some(error) = validator(&line) {
-            println!("{}", error);
+            writeln!(out, "{}", error)?;
             continue;
         }
         return Ok(line);
@@ -154,10 +236,13 @@ pub fn mandatory_validator(input: &str) -> Option<String> {
     }
 }
 
+/// Selects project and task by id or asks for them, `current` (project and
+/// task id) is offered as default answer
 pub async fn prompt_task_select(
     moco_client: &MocoClient,
     project: Option<i64>,
     task: Option<i64>,
+    current: Option<(i64, i64)>,
 ) -> Result<(Project, ProjectTask), Box<dyn Error>> {
     let projects = moco_client.get_assigned_projects().await?;
     let project = projects.iter().find(|p| p.id == project.unwrap_or(-1));
@@ -165,10 +250,19 @@ pub async fn prompt_task_select(
     let project = if let Some(p) = project {
         p
     } else {
+        require_input("--project")?;
+        let default =
+            current.and_then(|(project, _)| projects.iter().position(|p| p.id == project));
         let project_index = render_list_select(
             &projects,
             vec!["Index", "Customer", "Project", "Project ID"],
-            "Choose your Project: ",
+            &match default {
+                Some(index) => {
+                    format!("Choose your Project - Default '{}': ", projects[index].name)
+                }
+                None => "Choose your Project: ".to_string(),
+            },
+            default,
             &(|(index, project)| {
                 vec![
                     index.to_string(),
@@ -187,10 +281,19 @@ pub async fn prompt_task_select(
     let task = if let Some(t) = task {
         t
     } else {
+        require_input("--task")?;
+        let default = current.and_then(|(_, task)| project.tasks.iter().position(|t| t.id == task));
         let task_index = render_list_select(
             &project.tasks,
             vec!["Index", "Task", "Task ID"],
-            "Choose your Task: ",
+            &match default {
+                Some(index) => format!(
+                    "Choose your Task - Default '{}': ",
+                    project.tasks[index].name
+                ),
+                None => "Choose your Task: ".to_string(),
+            },
+            default,
             &(|(index, task)| vec![index.to_string(), task.name.clone(), task.id.to_string()]),
         )?;
         &project.tasks[task_index]
@@ -202,8 +305,15 @@ pub async fn prompt_task_select(
 pub async fn prompt_activity_select(
     moco_client: &MocoClient,
     activity: Option<i64>,
+    today: NaiveDate,
+    duration_format: DurationFormat,
 ) -> Result<Activity, Box<dyn Error>> {
-    let now = Utc::now().format("%Y-%m-%d").to_string();
+    if let Some(activity_id) = activity {
+        return moco_client.get_activity(&GetActivity { activity_id }).await;
+    }
+
+    require_input("--activity")?;
+    let now = today.format("%Y-%m-%d").to_string();
 
     print!("List activities from (YYYY-MM-DD) - Default 'today': ");
     std::io::stdout().flush()?;
@@ -222,40 +332,351 @@ pub async fn prompt_activity_select(
     }
 
     let activities = moco_client.get_activities(from, to, None, None).await?;
-    let activity = activities.iter().find(|a| a.id == activity.unwrap_or(-1));
 
-    let activity = if let Some(a) = activity {
-        a
-    } else {
-        let activity_index = render_list_select(
-            &activities,
+    let activity_index = render_list_select(
+        &activities,
+        vec![
+            "Index",
+            "Date",
+            "Duration",
+            "Project",
+            "Task",
+            "Description",
+        ],
+        "Choose your Activity: ",
+        None,
+        &(|(index, activity)| {
             vec![
-                "Index",
+                index.to_string(),
+                activity.date.clone(),
+                activity.duration().format(duration_format),
+                activity.project.name.clone(),
+                activity.task.name.clone(),
+                activity
+                    .description
+                    .as_ref()
+                    .unwrap_or(&String::new())
+                    .to_string(),
+            ]
+        }),
+    )?;
+
+    Ok(activities[activity_index].clone())
+}
+
+pub fn find_project_task(projects: &[Project], task_id: i64) -> Option<(Project, ProjectTask)> {
+    projects.iter().find_map(|project| {
+        project
+            .tasks
+            .iter()
+            .find(|task| task.id == task_id)
+            .map(|task| (project.clone(), task.clone()))
+    })
+}
+
+/// Remote url of the activity, or service and id if it has no url
+pub fn remote_link(activity: &Activity) -> String {
+    match (
+        activity.remote_url.as_str(),
+        &activity.remote_service,
+        &activity.remote_id,
+    ) {
+        (Some(url), _, _) if !url.is_empty() => url.to_string(),
+        (_, Some(service), Some(id)) => format!("{}:{}", service, id),
+        (_, Some(service), None) => service.clone(),
+        _ => String::new(),
+    }
+}
+
+/// FNV-1a hash, stable across builds so it can be used for `remote_id`s.
+pub fn stable_hash(input: &str) -> String {
+    let hash = input.bytes().fold(0xcbf29ce484222325_u64, |hash, byte| {
+        (hash ^ byte as u64).wrapping_mul(0x100000001b3)
+    });
+    format!("{:016x}", hash)
+}
+
+/// Removes activities whose remote id was already booked between `from` and `to`
+pub async fn remove_synced_activities(
+    moco_client: &MocoClient,
+    from: NaiveDate,
+    to: NaiveDate,
+    activities: Vec<CreateActivity>,
+) -> Result<Vec<CreateActivity>, Box<dyn Error>> {
+    let existing_activities = moco_client
+        .get_activities(
+            from.format("%Y-%m-%d").to_string(),
+            to.format("%Y-%m-%d").to_string(),
+            None,
+            None,
+        )
+        .await?;
+
+    Ok(activities
+        .into_iter()
+        .filter(|activity| {
+            !existing_activities.iter().any(|existing| {
+                activity.remote_id.is_some()
+                    && existing.remote_id == activity.remote_id
+                    && existing.remote_service == activity.remote_service
+            })
+        })
+        .collect())
+}
+
+/// Updates the duration of already synced activities whose (rounded) duration
+/// changed, e.g. after more time was clocked on a synced day. Returns `false`
+/// if the user declined the updates
+pub async fn update_synced_activities(
+    moco_client: &MocoClient,
+    activities: &[CreateActivity],
+    dry_run: bool,
+    yes: bool,
+    output: Option<Output>,
+    config: &AppConfig,
+) -> Result<bool, Box<dyn Error>> {
+    let dates = activities.iter().map(|activity| &activity.date);
+    let (Some(from), Some(to)) = (dates.clone().min(), dates.max()) else {
+        return Ok(true);
+    };
+    let existing_activities = moco_client
+        .get_activities(from.clone(), to.clone(), None, None)
+        .await?;
+
+    let duration_format = config.duration_format();
+    let mut updates = vec![];
+    for activity in activities {
+        let Some(existing) = existing_activities.iter().find(|existing| {
+            activity.remote_id.is_some()
+                && existing.remote_id == activity.remote_id
+                && existing.remote_service == activity.remote_service
+        }) else {
+            continue;
+        };
+        let duration = config.round(Duration::from_seconds(activity.seconds.unwrap_or_default()));
+        if duration != existing.duration() {
+            updates.push((existing, duration));
+        }
+    }
+    if updates.is_empty() {
+        return Ok(true);
+    }
+
+    print_info(output, "Changed durations: ");
+    render_output(
+        output,
+        ["Date", "Duration", "Updated", "Description"]
+            .iter()
+            .map(|str| str.to_string())
+            .collect(),
+        updates
+            .iter()
+            .map(|(existing, duration)| {
+                vec![
+                    existing.date.clone(),
+                    existing.duration().format(duration_format),
+                    duration.format(duration_format),
+                    existing.description.clone().unwrap_or_default(),
+                ]
+            })
+            .collect(),
+        None,
+        &updates
+            .iter()
+            .map(|(existing, _)| *existing)
+            .collect::<Vec<&Activity>>(),
+    )?;
+    print_info(output, "");
+
+    if dry_run {
+        return Ok(true);
+    }
+    let question = format!("Update the duration of {} activities?", updates.len());
+    if !confirm(&question, yes)? {
+        return Ok(false);
+    }
+
+    let mut failed = 0;
+    for (existing, duration) in &updates {
+        let edit = EditActivity {
+            activity_id: existing.id,
+            project_id: existing.project.id,
+            task_id: existing.task.id,
+            date: existing.date.clone(),
+            description: existing.description.clone().unwrap_or_default(),
+            seconds: duration.seconds(),
+            ..Default::default()
+        };
+        if let Err(e) = moco_client.edit_activity(&edit).await {
+            eprintln!("Could not update activity {}: {}", existing.id, e);
+            failed += 1;
+        }
+    }
+    if failed > 0 {
+        return Err(format!("{} of {} updates failed", failed, updates.len()).into());
+    }
+
+    Ok(true)
+}
+
+/// Lets the user accept, edit or drop a suggested activity
+pub async fn prompt_activity_review(
+    moco_client: &MocoClient,
+    mut activity: CreateActivity,
+    project: &Project,
+    task: &ProjectTask,
+    duration_format: DurationFormat,
+) -> Result<Option<CreateActivity>, Box<dyn Error>> {
+    require_input("--yes")?;
+
+    let mut project_name = project.name.clone();
+    let mut task_name = task.name.clone();
+
+    loop {
+        let duration = Duration::from_seconds(activity.seconds.unwrap_or(0));
+        let mut out = interactive_output();
+        writeln!(out)?;
+        write_table(
+            &mut out,
+            vec![vec![
+                activity.date.clone(),
+                duration.format(duration_format),
+                format!("{} / {}", project_name, task_name),
+                activity.description.clone(),
+            ]],
+        );
+
+        let answer = ask_question("[a]ccept, [e]dit, [d]rop - Default 'accept': ", &|answer| {
+            if matches!(answer, "" | "a" | "e" | "d") {
+                None
+            } else {
+                Some("Answer with a, e or d".to_string())
+            }
+        })?;
+
+        match answer.as_str() {
+            "d" => return Ok(None),
+            "e" => {
+                let answer = ask_question(
+                    &format!(
+                        "Duration - Default '{}': ",
+                        duration.format(duration_format)
+                    ),
+                    &|answer| {
+                        if answer.is_empty() {
+                            None
+                        } else {
+                            answer.parse::<Duration>().err()
+                        }
+                    },
+                )?;
+                if !answer.is_empty() {
+                    activity.seconds = Some(answer.parse::<Duration>()?.seconds());
+                }
+
+                let description =
+                    ask_question("Description - Default 'current': ", &optional_validator)?;
+                if !description.is_empty() {
+                    activity.description = description;
+                }
+
+                let change_task = ask_question("Change task? (y/N): ", &optional_validator)?;
+                if change_task.eq_ignore_ascii_case("y") {
+                    let (project, task) = prompt_task_select(moco_client, None, None, None).await?;
+                    activity.project_id = project.id;
+                    activity.task_id = task.id;
+                    project_name = project.name;
+                    task_name = task.name;
+                }
+            }
+            _ => return Ok(Some(activity)),
+        }
+    }
+}
+
+pub async fn sync_activities(
+    moco_client: &MocoClient,
+    from: NaiveDate,
+    to: NaiveDate,
+    activities: Vec<CreateActivity>,
+    dry_run: bool,
+    output: Option<Output>,
+    config: &AppConfig,
+) -> Result<(), Box<dyn Error>> {
+    let mut activities = remove_synced_activities(moco_client, from, to, activities).await?;
+
+    let duration_format = config.duration_format();
+    let durations: Vec<Duration> = activities
+        .iter_mut()
+        .map(|activity| {
+            let duration = activity
+                .seconds
+                .map(Duration::from_seconds)
+                .or(activity.hours.map(Duration::from_hours))
+                .unwrap_or_default();
+            activity.seconds = Some(config.round(duration).seconds());
+            activity.hours = None;
+            duration
+        })
+        .collect();
+
+    if dry_run {
+        print_info(output, "Planned sync: ");
+    } else {
+        print_info(output, "Sync plan: ");
+    }
+    print_info(
+        output,
+        &format!(
+            "From {} to {}",
+            from.format("%d.%m.%y"),
+            to.format("%d.%m.%y")
+        ),
+    );
+
+    if activities.is_empty() && resolve_output(output) == Output::Table {
+        println!("Nothing, everything seems to be synced!")
+    } else {
+        render_output(
+            output,
+            [
                 "Date",
                 "Duration",
-                "Project",
-                "Task",
+                "Rounded",
                 "Description",
-            ],
-            "Choose your Activity: ",
-            &(|(index, activity)| {
-                vec![
-                    index.to_string(),
-                    activity.date.clone(),
-                    activity.hours.to_string(),
-                    activity.project.name.clone(),
-                    activity.task.name.clone(),
-                    activity
-                        .description
-                        .as_ref()
-                        .unwrap_or(&String::new())
-                        .to_string(),
-                ]
-            }),
+                "Project ID",
+                "Task ID",
+            ]
+            .iter()
+            .map(|str| str.to_string())
+            .collect(),
+            activities
+                .iter()
+                .zip(durations)
+                .map(|(activity, duration)| {
+                    vec![
+                        activity.date.clone(),
+                        duration.format(duration_format),
+                        Duration::from_seconds(activity.seconds.unwrap_or_default())
+                            .format(duration_format),
+                        activity.description.clone(),
+                        activity.project_id.to_string(),
+                        activity.task_id.to_string(),
+                    ]
+                })
+                .collect(),
+            None,
+            &activities,
         )?;
+    }
+    print_info(output, "");
 
-        &activities[activity_index]
-    };
+    if !dry_run {
+        for activity in activities {
+            moco_client.create_activity(&activity).await?;
+        }
+        print_info(output, "Synced!");
+    }
 
-    Ok(activity.clone())
+    Ok(())
 }
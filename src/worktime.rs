@@ -0,0 +1,172 @@
+use std::collections::BTreeMap;
+
+use chrono::NaiveTime;
+use serde::Serialize;
+
+use crate::config::AppConfig;
+use crate::duration::{parse_time, Duration, DurationFormat};
+use crate::moco::model::{Activity, Presence};
+
+/// Break of at least `minimum` when working longer than `after`
+#[derive(Debug, Clone, Copy)]
+pub struct BreakRule {
+    pub after: Duration,
+    pub minimum: Duration,
+}
+
+/// Working time rules, the German working time act unless configured otherwise
+#[derive(Debug, Clone)]
+pub struct Rules {
+    pub breaks: Vec<BreakRule>,
+    pub max_daily: Option<Duration>,
+}
+
+impl Rules {
+    pub fn new(config: &AppConfig) -> Self {
+        let worktime = config.worktime.clone().unwrap_or_default();
+        let parse = |value: &str| match value.parse::<Duration>() {
+            Ok(duration) => Some(duration),
+            Err(e) => {
+                log::warn!("Invalid working time rule \"{}\": {}", value, e);
+                None
+            }
+        };
+
+        let breaks = match worktime.breaks {
+            Some(breaks) => breaks
+                .iter()
+                .filter_map(|rule| {
+                    Some(BreakRule {
+                        after: parse(&rule.after)?,
+                        minimum: parse(&rule.minimum)?,
+                    })
+                })
+                .collect(),
+            None => vec![
+                BreakRule {
+                    after: Duration::from_hours(6.0),
+                    minimum: Duration::from_seconds(30 * 60),
+                },
+                BreakRule {
+                    after: Duration::from_hours(9.0),
+                    minimum: Duration::from_seconds(45 * 60),
+                },
+            ],
+        };
+        let max_daily = match worktime.max_daily {
+            Some(max_daily) => parse(&max_daily),
+            None => Some(Duration::from_hours(10.0)),
+        }
+        .filter(|max_daily| !max_daily.is_zero());
+
+        Rules { breaks, max_daily }
+    }
+
+    /// Longest break required for the worked time
+    pub fn required_break(&self, worked: Duration) -> Option<BreakRule> {
+        self.breaks
+            .iter()
+            .filter(|rule| worked > rule.after)
+            .max_by_key(|rule| rule.minimum)
+            .copied()
+    }
+}
+
+/// Worked time and breaks of a day, from presences and booked activities
+#[derive(Debug, Default)]
+pub struct Day {
+    pub date: String,
+    pub present: Duration,
+    pub breaks: Duration,
+    pub booked: Duration,
+    pub has_presences: bool,
+}
+
+#[derive(Debug, Serialize)]
+pub struct Violation {
+    pub date: String,
+    pub rule: String,
+    #[serde(rename = "actual_seconds")]
+    pub actual: Duration,
+    #[serde(rename = "limit_seconds")]
+    pub limit: Duration,
+}
+
+/// Days of the presences and activities, open presences of `today` last until `now`
+pub fn days(
+    presences: &[Presence],
+    activities: &[Activity],
+    today: &str,
+    now: NaiveTime,
+) -> Vec<Day> {
+    let mut days: BTreeMap<String, Day> = BTreeMap::new();
+
+    let mut presences: Vec<&Presence> = presences.iter().collect();
+    presences.sort_by(|a, b| (&a.date, &a.from).cmp(&(&b.date, &b.from)));
+    let mut last_end: Option<(String, NaiveTime)> = None;
+    for presence in presences {
+        let now = (presence.date == today).then_some(now);
+        let day = days.entry(presence.date.clone()).or_default();
+        day.has_presences = true;
+        day.present = day.present + presence.duration(now);
+
+        let Ok(from) = parse_time(&presence.from) else {
+            continue;
+        };
+        if let Some((date, end)) = &last_end {
+            if *date == presence.date {
+                day.breaks = day.breaks + Duration::between(*end, from).unwrap_or_default();
+            }
+        }
+        let end = match presence.to.as_deref() {
+            Some(to) if !to.is_empty() => parse_time(to).ok(),
+            _ => now,
+        };
+        last_end = end.map(|end| (presence.date.clone(), end));
+    }
+
+    for activity in activities {
+        let day = days.entry(activity.date.clone()).or_default();
+        day.booked = day.booked + activity.duration();
+    }
+
+    days.into_iter()
+        .map(|(date, day)| Day { date, ..day })
+        .collect()
+}
+
+/// Violations of a day, breaks are only known from presences
+pub fn check(rules: &Rules, day: &Day, duration_format: DurationFormat) -> Vec<Violation> {
+    let mut violations = vec![];
+
+    if day.has_presences {
+        if let Some(rule) = rules.required_break(day.present) {
+            if day.breaks < rule.minimum {
+                violations.push(Violation {
+                    date: day.date.clone(),
+                    rule: format!("Break after {}", rule.after.format(duration_format)),
+                    actual: day.breaks,
+                    limit: rule.minimum,
+                });
+            }
+        }
+    }
+
+    if let Some(max_daily) = rules.max_daily {
+        let (source, worked) = if day.booked > day.present {
+            ("booked", day.booked)
+        } else {
+            ("present", day.present)
+        };
+        if worked > max_daily {
+            violations.push(Violation {
+                date: day.date.clone(),
+                rule: format!("Maximum per day ({})", source),
+                actual: worked,
+                limit: max_daily,
+            });
+        }
+    }
+
+    violations
+}